@@ -0,0 +1,56 @@
+#![feature(futures_api, test)]
+
+extern crate test;
+
+use futures::future::Future;
+use futures::stream::Stream;
+use futures::task::noop_waker_ref;
+use futures_native_timers::{Delay, Interval, MockTimer, TimerSource};
+use std::pin::Pin;
+use std::time::Duration;
+use test::Bencher;
+
+// polling a timer that isn't due yet, over and over with the same waker - the case the
+// registered waker is cached for.
+
+#[bench]
+fn poll_pending_delay(b: &mut Bencher) {
+    let mut delay = Delay::new(Duration::from_secs(60 * 60));
+    let waker = noop_waker_ref();
+
+    b.iter(|| Pin::new(&mut delay).poll(waker).is_pending());
+}
+
+#[bench]
+fn poll_pending_interval(b: &mut Bencher) {
+    let mut interval = Interval::new(Duration::from_secs(60 * 60));
+    let waker = noop_waker_ref();
+
+    b.iter(|| Pin::new(&mut interval).poll_next(waker).is_pending());
+}
+
+// yielding a tick that is already due - with and without reading the clock for it.
+
+#[bench]
+fn poll_ready_interval(b: &mut Bencher) {
+    let mock = MockTimer::new();
+    let mut interval = mock.interval(Duration::from_millis(1));
+    let waker = noop_waker_ref();
+
+    b.iter(|| {
+        mock.advance(Duration::from_millis(1));
+        Pin::new(&mut interval).poll_next(waker).is_ready()
+    });
+}
+
+#[bench]
+fn poll_ready_lightweight_interval(b: &mut Bencher) {
+    let mock = MockTimer::new();
+    let mut interval = mock.interval(Duration::from_millis(1)).lightweight();
+    let waker = noop_waker_ref();
+
+    b.iter(|| {
+        mock.advance(Duration::from_millis(1));
+        Pin::new(&mut interval).poll_next(waker).is_ready()
+    });
+}
@@ -0,0 +1,42 @@
+#![feature(futures_api, async_await, await_macro)]
+
+use futures_native_timers::{Delay, FutureExt, Interval};
+use std::time::{Duration, Instant};
+
+use futures::executor::block_on;
+use futures::future;
+use futures::prelude::*;
+
+#[derive(Debug)]
+enum Error {
+    TooSlow,
+}
+
+fn main() {
+    let start = Instant::now();
+
+    // an interval that stops by itself after five ticks.
+    let work = async {
+        let mut ticks = Interval::new(Duration::from_millis(50)).take_ticks(5);
+        while let Some(_) = await!(ticks.next()) {
+            println!("tick at {:?}", start.elapsed());
+        }
+    };
+    block_on(work);
+
+    // one delay, reset and awaited again instead of making a new one every time.
+    let work = async {
+        let mut delay = Delay::new(Duration::from_millis(20));
+        for i in 0..3 {
+            await!(&mut delay);
+            println!("delay {} done at {:?}", i, start.elapsed());
+            delay.reset(Duration::from_millis(20));
+        }
+    };
+    block_on(work);
+
+    // a timeout that fails with an error of our own.
+    let res: Result<(), Error> =
+        block_on(future::empty().timeout_map(Duration::from_millis(100), || Error::TooSlow));
+    println!("{:?}", res);
+}
@@ -0,0 +1,112 @@
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::prelude::*;
+use futures::stream::FusedStream;
+use futures::task::{Poll, Waker};
+
+use super::Delay;
+
+/// A stream of exponentially growing delays, for retry loops.
+///
+/// The first item is yielded `base` after the stream is first polled, the next
+/// `base * factor` after that, and so on, with every delay capped at `max`. Each
+/// item is the delay that was just waited out.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Backoff {
+    delay: Delay,
+    base: Duration,
+    factor: u32,
+    max: Duration,
+    current: Duration,
+    armed: bool,
+}
+
+impl Backoff {
+    pub fn new(base: Duration, factor: u32, max: Duration) -> Self {
+        let base = base.min(max);
+
+        Backoff {
+            delay: Delay::new(base),
+            base,
+            factor,
+            max,
+            current: base,
+            armed: false,
+        }
+    }
+
+    /// Goes back to waiting `base` - e.g. after a retry succeeded.
+    ///
+    /// A wait that is already in progress is abandoned, and the next one starts when
+    /// the stream is next polled.
+    pub fn reset(&mut self) {
+        self.current = self.base;
+        self.armed = false;
+    }
+
+    /// Returns how long the next (or current) wait is.
+    pub fn current(&self) -> Duration {
+        self.current
+    }
+}
+
+impl Stream for Backoff {
+    type Item = Duration;
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        if !self.armed {
+            let current = self.current;
+            self.delay.reset(current);
+            self.armed = true;
+        }
+
+        match Pin::new(&mut self.delay).poll(lw) {
+            Poll::Ready(()) => {
+                let waited = self.current;
+                self.current = waited
+                    .checked_mul(self.factor)
+                    .map_or(self.max, |next| next.min(self.max));
+                self.armed = false;
+
+                Poll::Ready(Some(waited))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl FusedStream for Backoff {
+    fn is_terminated(&self) -> bool {
+        false
+    }
+}
+
+impl Unpin for Backoff {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::time::Instant;
+
+    #[test]
+    fn backoff() {
+        let ms = Duration::from_millis;
+        let mut backoff = Backoff::new(ms(10), 2, ms(40));
+
+        let start = Instant::now();
+        let waits: Vec<_> = (0..4).map(|_| block_on(backoff.next()).unwrap()).collect();
+        assert_eq!(waits, vec![ms(10), ms(20), ms(40), ms(40)]);
+        assert!(start.elapsed() >= ms(110));
+        assert_eq!(backoff.current(), ms(40));
+
+        backoff.reset();
+        assert_eq!(backoff.current(), ms(10));
+
+        let start = Instant::now();
+        assert_eq!(block_on(backoff.next()), Some(ms(10)));
+        assert!(start.elapsed() < ms(40));
+    }
+}
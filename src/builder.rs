@@ -0,0 +1,166 @@
+use std::io;
+use std::time::{Duration, Instant};
+
+use super::{until, Clock, Delay, Interval, MissedTickBehavior, QosClass, Timer, WakeStrategy};
+
+/// Configures a [`Delay`] or [`Interval`] in one place, for when the plain
+/// constructors aren't enough.
+///
+/// Set whichever options matter, then finish with [`TimerBuilder::delay`] or
+/// [`TimerBuilder::interval`].
+#[derive(Clone, Debug, Default)]
+pub struct TimerBuilder {
+    clock: Clock,
+    leeway: Duration,
+    qos: QosClass,
+    deadline: Option<Instant>,
+    period: Duration,
+    missed_tick_behavior: MissedTickBehavior,
+    wake_strategy: WakeStrategy,
+}
+
+impl TimerBuilder {
+    pub fn new() -> Self {
+        TimerBuilder::default()
+    }
+
+    /// The clock the timer is measured against. Defaults to [`Clock::Monotonic`].
+    pub fn clock(mut self, clock: Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// How late the system may fire the timer, so it can coalesce wakeups. Defaults
+    /// to zero, and is ignored on Linux.
+    pub fn leeway(mut self, leeway: Duration) -> Self {
+        self.leeway = leeway;
+        self
+    }
+
+    /// How urgently the system should run the timer's callbacks. Defaults to
+    /// [`QosClass::Default`], and is ignored everywhere but macOS.
+    pub fn qos_class(mut self, qos: QosClass) -> Self {
+        self.qos = qos;
+        self
+    }
+
+    /// When a delay fires, or when an interval ticks for the first time.
+    ///
+    /// Without a deadline a delay fires one period after it is first polled, and so
+    /// does the first tick of an interval.
+    pub fn deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// How long a delay waits, or the time between an interval's ticks.
+    pub fn period(mut self, period: Duration) -> Self {
+        self.period = period;
+        self
+    }
+
+    /// How an interval catches up on ticks it missed. Delays ignore this.
+    pub fn missed_tick_behavior(mut self, behavior: MissedTickBehavior) -> Self {
+        self.missed_tick_behavior = behavior;
+        self
+    }
+
+    /// Where the timer's task is woken from when it fires. Defaults to
+    /// [`WakeStrategy::Inline`].
+    pub fn wake_strategy(mut self, strategy: WakeStrategy) -> Self {
+        self.wake_strategy = strategy;
+        self
+    }
+
+    /// Builds a [`Delay`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see
+    /// [`TimerBuilder::try_delay`] for a version that returns the error instead.
+    pub fn delay(&self) -> Delay {
+        self.try_delay().expect("error creating native timer!")
+    }
+
+    /// Like [`TimerBuilder::delay`], but returns the os error if the native timer
+    /// can't be created.
+    pub fn try_delay(&self) -> io::Result<Delay> {
+        let delay = match self.deadline {
+            Some(deadline) => until(deadline),
+            None => self.period,
+        };
+
+        Ok(Delay::from_timer(self.timer()?, delay, self.deadline))
+    }
+
+    /// Builds an [`Interval`].
+    ///
+    /// # Panics
+    ///
+    /// Panics if the period is zero, or if the native timer can't be created - see
+    /// [`TimerBuilder::try_interval`] for a version that returns the error instead.
+    pub fn interval(&self) -> Interval {
+        self.try_interval().expect("error creating native timer!")
+    }
+
+    /// Like [`TimerBuilder::interval`], but returns the os error if the native timer
+    /// can't be created.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the period is zero.
+    pub fn try_interval(&self) -> io::Result<Interval> {
+        assert!(
+            self.period > Duration::from_secs(0),
+            "an interval needs a non-zero period!"
+        );
+
+        let mut interval = Interval::from_timer(self.timer()?, self.period, self.deadline);
+        interval.set_missed_tick_behavior(self.missed_tick_behavior);
+        Ok(interval)
+    }
+
+    fn timer(&self) -> io::Result<Timer> {
+        let mut timer = Timer::try_new(self.clock)?;
+        timer.set_leeway(self.leeway);
+        timer.set_qos_class(self.qos);
+        timer.set_wake_strategy(self.wake_strategy);
+        Ok(timer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+
+    #[test]
+    fn timer_builder() {
+        let start = Instant::now();
+
+        let delay = TimerBuilder::new()
+            .period(Duration::from_millis(20))
+            .leeway(Duration::from_millis(5))
+            .qos_class(QosClass::UserInteractive)
+            .delay();
+        block_on(delay);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let deadline = Instant::now() + Duration::from_millis(20);
+        let delay = TimerBuilder::new().deadline(deadline).delay();
+        block_on(delay);
+        assert!(Instant::now() >= deadline);
+
+        let builder = TimerBuilder::new()
+            .period(Duration::from_millis(10))
+            .clock(Clock::Realtime)
+            .missed_tick_behavior(MissedTickBehavior::Burst);
+        let stream = builder.interval();
+        assert_eq!(stream.period(), Duration::from_millis(10));
+        assert_eq!(stream.missed_tick_behavior(), MissedTickBehavior::Burst);
+
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+    }
+}
@@ -0,0 +1,50 @@
+/// The clock a timer measures its delays against.
+///
+/// Only Linux lets the clock be chosen when the timer is created. macOS starts
+/// `Realtime` timers from the wall clock instead, and Windows, the BSDs and the
+/// `fallback` backend fall back to the monotonic clock. In the browser both clocks are
+/// the same one.
+///
+/// Falling back prints a warning to stderr, once per process, the first time a
+/// `Realtime` timer is created - the only thing the crate ever prints, since a timer
+/// that quietly ignores a clock change is otherwise hard to track down.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Clock {
+    /// A clock that never jumps, and keeps counting while the system is suspended or
+    /// its time is changed.
+    ///
+    /// This is the default.
+    Monotonic,
+    /// The system's wall clock, which follows changes to the system time.
+    Realtime,
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::Monotonic
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Delay, Interval};
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn realtime_clock() {
+        let start = Instant::now();
+
+        block_on(Delay::with_clock(
+            Duration::from_millis(20),
+            Clock::Realtime,
+        ));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let stream = Interval::with_clock(Duration::from_millis(10), Clock::Realtime);
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+    }
+}
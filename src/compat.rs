@@ -0,0 +1,153 @@
+//! Stand-ins for the `Delay` and `Interval` of the `futures-timer` crate, driven by
+//! this crate's native timers.
+//!
+//! The types here have `futures-timer`'s constructors and methods, so code written
+//! against it only has to change its imports:
+//!
+//! - `Delay::new(dur)` and `Delay::new_at(at)` create a delay, `Delay::when` returns
+//!   when it fires, and `Delay::reset(at)` moves that. It resolves to an
+//!   `io::Result<()>`, like the original's.
+//! - `Interval::new(dur)` and `Interval::new_at(at, dur)` create an interval, which
+//!   yields `()` for every tick.
+//!
+//! Like `futures-timer`'s - and unlike this crate's own - both start counting when
+//! they are created, not when they are first polled.
+//!
+//! The difference is what drives them. `futures-timer` runs every timer off one
+//! global helper thread, while each of these is an os timer of its own, firing from
+//! the os's (or this crate's) callbacks. The `io::Result` is always `Ok` here - the
+//! original errors once its helper thread is gone, which can't happen to an os timer.
+//! And creating one panics if the os won't give out any more timers.
+
+use std::io;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use futures::future::FusedFuture;
+use futures::prelude::*;
+use futures::stream::FusedStream;
+use futures::task::{Poll, Waker};
+
+use crate::{saturating_add, until};
+
+/// A `futures-timer` style delay, which fires once its deadline has passed.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Delay {
+    inner: crate::Delay,
+    when: Instant,
+}
+
+impl Delay {
+    /// Creates a delay that fires `dur` from now.
+    pub fn new(dur: Duration) -> Self {
+        Delay::new_at(saturating_add(Instant::now(), dur))
+    }
+
+    /// Creates a delay that fires at `at`.
+    pub fn new_at(at: Instant) -> Self {
+        Delay {
+            inner: crate::Delay::at(at),
+            when: at,
+        }
+    }
+
+    /// Returns when the delay fires.
+    pub fn when(&self) -> Instant {
+        self.when
+    }
+
+    /// Moves the delay to fire at `at` instead, re-arming the existing timer whether or
+    /// not it has already fired.
+    pub fn reset(&mut self, at: Instant) {
+        self.when = at;
+        self.inner.reset(until(at));
+    }
+}
+
+impl Future for Delay {
+    type Output = io::Result<()>;
+
+    fn poll(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+        Pin::new(&mut self.inner).poll(lw).map(Ok)
+    }
+}
+
+impl FusedFuture for Delay {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl Unpin for Delay {}
+
+/// A `futures-timer` style interval, which yields `()` for every tick.
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct Interval {
+    inner: crate::Interval,
+}
+
+impl Interval {
+    /// Creates an interval that first ticks `dur` from now, and every `dur` after that.
+    pub fn new(dur: Duration) -> Self {
+        Interval::new_at(saturating_add(Instant::now(), dur), dur)
+    }
+
+    /// Creates an interval that first ticks at `at`, and every `dur` after that.
+    pub fn new_at(at: Instant, dur: Duration) -> Self {
+        Interval {
+            inner: crate::Interval::new_at(at, dur),
+        }
+    }
+}
+
+impl Stream for Interval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner)
+            .poll_next(lw)
+            .map(|tick| tick.map(|_| ()))
+    }
+}
+
+impl FusedStream for Interval {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl Unpin for Interval {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::thread;
+
+    #[test]
+    fn compat_timers() {
+        use crate::compat;
+
+        // these count from creation, like `futures-timer`'s.
+        let start = Instant::now();
+        let delay = compat::Delay::new(Duration::from_millis(30));
+        assert!(delay.when() >= start + Duration::from_millis(30));
+        thread::sleep(Duration::from_millis(30));
+
+        block_on(delay).unwrap();
+        assert!(start.elapsed() < Duration::from_millis(55));
+
+        let mut delay = compat::Delay::new_at(Instant::now() + Duration::from_secs(10));
+        let at = Instant::now() + Duration::from_millis(20);
+        delay.reset(at);
+        assert_eq!(delay.when(), at);
+        block_on(&mut delay).unwrap();
+        assert!(Instant::now() >= at);
+
+        let stream = compat::Interval::new(Duration::from_millis(10));
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks, vec![(), (), ()]);
+    }
+}
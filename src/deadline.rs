@@ -0,0 +1,127 @@
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::future::FusedFuture;
+use futures::prelude::*;
+use futures::task::{Poll, Waker};
+
+use super::Delay;
+
+/// A rolling deadline for a `select!` loop.
+///
+/// A deadline is a delay that remembers its duration. Once its branch has been taken
+/// it stays terminated, so `select!` skips it, until [`Deadline::reset`] pushes it
+/// back to a full duration from now - e.g. after every message, for an idle timeout
+/// that keeps the loop going for as long as there is activity.
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct Deadline {
+    delay: Delay,
+    duration: Duration,
+}
+
+impl Deadline {
+    /// Creates a deadline that passes `duration` from now.
+    ///
+    /// Unlike a plain delay it counts from its creation, not its first poll, so one
+    /// that is created before a loop starts counting straight away.
+    pub fn new(duration: Duration) -> Self {
+        Deadline {
+            delay: Delay::new_armed(duration),
+            duration,
+        }
+    }
+
+    /// Moves the deadline to a full duration from now, re-arming the existing timer
+    /// whether or not it has already passed.
+    pub fn reset(&mut self) {
+        self.delay.reset(self.duration);
+    }
+
+    /// Returns the duration the deadline is reset to.
+    pub fn duration(&self) -> Duration {
+        self.duration
+    }
+
+    /// Changes the duration the deadline is reset to, from the next reset on.
+    pub fn set_duration(&mut self, duration: Duration) {
+        self.duration = duration;
+    }
+
+    /// Returns how much time is left before the deadline passes.
+    pub fn remaining(&self) -> Duration {
+        self.delay.remaining()
+    }
+
+    /// Returns whether the deadline has passed, without polling it.
+    pub fn is_elapsed(&self) -> bool {
+        self.delay.is_elapsed()
+    }
+}
+
+impl Future for Deadline {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+        Pin::new(&mut self.delay).poll(lw)
+    }
+}
+
+impl FusedFuture for Deadline {
+    fn is_terminated(&self) -> bool {
+        self.delay.is_terminated()
+    }
+}
+
+impl Unpin for Deadline {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interval;
+    use futures::executor::block_on;
+    use std::time::Instant;
+
+    #[test]
+    fn rolling_deadline() {
+        use futures::select;
+
+        let start = Instant::now();
+        let mut deadline = Deadline::new(Duration::from_millis(50));
+        let mut events = Interval::new(Duration::from_millis(10)).take_ticks(5);
+
+        let work = async {
+            let mut seen = 0;
+            let mut passed = 0;
+            loop {
+                select! {
+                    event = events.next() => match event {
+                        // every event pushes the deadline back.
+                        Some(_) => {
+                            seen += 1;
+                            deadline.reset();
+                        }
+                        None => {}
+                    },
+                    _ = deadline => {
+                        passed += 1;
+                        if passed == 2 {
+                            break;
+                        }
+
+                        // the same deadline, re-armed in place for another round.
+                        deadline.reset();
+                    }
+                }
+            }
+
+            seen
+        };
+
+        assert_eq!(block_on(work), 5);
+
+        // 5 events 10ms apart, then two full deadlines after the last one.
+        assert!(start.elapsed() >= Duration::from_millis(150));
+        assert_eq!(deadline.duration(), Duration::from_millis(50));
+    }
+}
@@ -0,0 +1,152 @@
+use std::fmt;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::prelude::*;
+use futures::stream::FusedStream;
+use futures::task::{Poll, Waker};
+use pin_utils::{unsafe_pinned, unsafe_unpinned};
+
+use super::Delay;
+
+/// A stream that only yields an item of another once no newer one has arrived for
+/// `period`.
+///
+/// Created by the `debounce` method on this crate's `StreamExt`. Every item restarts
+/// the wait and replaces the one that was waiting, so a burst only yields its last
+/// item, once the burst is over.
+#[must_use = "streams do nothing unless polled"]
+pub struct Debounce<S: Stream> {
+    stream: S,
+    // re-armed for every item, rather than creating a timer each time.
+    delay: Delay,
+    period: Duration,
+    pending: Option<S::Item>,
+    done: bool,
+}
+
+impl<S: Stream> Debounce<S> {
+    unsafe_pinned!(stream: S);
+
+    unsafe_pinned!(delay: Delay);
+
+    unsafe_unpinned!(pending: Option<S::Item>);
+
+    unsafe_unpinned!(done: bool);
+
+    pub(crate) fn new(stream: S, period: Duration) -> Self {
+        Debounce {
+            stream,
+            delay: Delay::new(period),
+            period,
+            pending: None,
+            done: false,
+        }
+    }
+
+    /// Returns the wrapped stream, dropping any item that was waiting.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Stream + Unpin> Unpin for Debounce<S> {}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Debounce<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Debounce")
+            .field("stream", &self.stream)
+            .field("period", &self.period)
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl<S: Stream> Stream for Debounce<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Option<Self::Item>> {
+        let period = self.period;
+
+        while !self.done {
+            match self.as_mut().stream().poll_next(w) {
+                Poll::Ready(Some(item)) => {
+                    *self.as_mut().pending() = Some(item);
+                    self.as_mut().delay().reset(period);
+                }
+                Poll::Ready(None) => *self.as_mut().done() = true,
+                Poll::Pending => break,
+            }
+        }
+
+        // once the stream has ended nothing can replace the waiting item, so it goes
+        // out straight away.
+        if self.done {
+            return Poll::Ready(self.as_mut().pending().take());
+        }
+
+        if self.pending.is_none() {
+            return Poll::Pending;
+        }
+
+        match self.as_mut().delay().poll(w) {
+            Poll::Ready(()) => Poll::Ready(self.as_mut().pending().take()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<S: Stream> FusedStream for Debounce<S> {
+    fn is_terminated(&self) -> bool {
+        self.done && self.pending.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamExt as _;
+    use futures::executor::block_on;
+    use futures::stream;
+    use std::time::Instant;
+
+    #[test]
+    fn debounce_bursts() {
+        use futures::channel::mpsc;
+        use std::thread;
+
+        let (send, recv) = mpsc::unbounded();
+        thread::spawn(move || {
+            // two rapid bursts, then a few items spaced further apart than the period.
+            for burst in &[0..5, 5..10] {
+                for i in burst.clone() {
+                    send.unbounded_send(i).unwrap();
+                    thread::sleep(Duration::from_millis(5));
+                }
+                thread::sleep(Duration::from_millis(150));
+            }
+
+            for i in 10..13 {
+                send.unbounded_send(i).unwrap();
+                thread::sleep(Duration::from_millis(150));
+            }
+        });
+
+        let items: Vec<_> = block_on(recv.debounce(Duration::from_millis(50)).collect());
+        assert_eq!(items, vec![4, 9, 10, 11, 12]);
+    }
+
+    #[test]
+    fn debounce_end_of_stream() {
+        let start = Instant::now();
+        let items: Vec<_> = block_on(
+            stream::iter(0..10)
+                .debounce(Duration::from_secs(10))
+                .collect(),
+        );
+
+        // the stream ends before the period is up, which flushes the last item.
+        assert_eq!(items, vec![9]);
+        assert!(start.elapsed() < Duration::from_secs(10));
+    }
+}
@@ -1,48 +1,482 @@
+use std::fmt;
+use std::io;
 use std::pin::Pin;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use futures::future::FusedFuture;
 use futures::prelude::*;
+use futures::stream::{FusedStream, FuturesUnordered};
 use futures::task::{Poll, Waker};
 
-use super::Timer;
+#[cfg(feature = "wheel")]
+use super::TimerWheel;
+use super::{saturating_add, until, Clock, Interval, RawTimer, TimeBase, Timer};
 
-#[derive(Debug)]
+/// Returns a future that completes after `delay`. This is a shorthand for
+/// [`Delay::new`].
+pub fn sleep(delay: Duration) -> Delay {
+    Delay::new(delay)
+}
+
+/// Returns a future that completes at `deadline`. This is a shorthand for
+/// [`Delay::at`].
+pub fn sleep_until(deadline: Instant) -> Delay {
+    Delay::at(deadline)
+}
+
+/// Returns a stream that yields the index of each of `delays` as it completes, in the
+/// order they fire, and ends once all of them have.
+///
+/// Delays that complete between two polls of the stream are yielded in no particular
+/// order.
+pub fn join_ordered(delays: Vec<Delay>) -> JoinOrdered {
+    let delays = delays
+        .into_iter()
+        .enumerate()
+        .map(|(index, delay)| DelayWith {
+            delay,
+            value: Some(index),
+        })
+        .collect();
+
+    JoinOrdered { delays }
+}
+
+/// A future that completes once its native timer fires.
+///
+/// Once a delay has completed, polling it again keeps returning `Ready` and
+/// `is_terminated` returns `true`, until [`Delay::reset`] re-arms it. After a reset
+/// the same delay can be awaited again, so one delay can be reused in a loop instead
+/// of creating a new timer every time round.
+///
+/// A delay only arms its native timer when it is first polled, so one built well
+/// before it is awaited doesn't count down in the meantime. Use [`Delay::new_armed`]
+/// (or [`Delay::at`], for a fixed deadline) when the time before the first poll
+/// should count too.
+///
+/// A new delay only allocates when its thread has no idle timer to reuse - dropped
+/// timers go back to a small per-thread pool, along with their shared state, so code
+/// that keeps creating delays on one thread stops allocating once the pool is warm.
+/// The shared state can't live inside the delay instead: several backends can still
+/// run a timer's callback after the timer is dropped, and need the state to outlive
+/// it.
+///
+/// Delays (and intervals) are `Send` and `Sync` on every platform, and keep firing
+/// after being moved to another thread. The one exception is Linux with the default
+/// `signal-thread` feature turned off, where a timer's signals go to the thread that
+/// created it, and stop arriving once that thread has exited - unless it was created
+/// through a [`Reactor`](crate::Reactor), whose thread never exits. A delay whose
+/// expiry was lost like that still completes - the wake thread notices the missing
+/// signal about a second after the delay was due, and wakes its task then. Intervals
+/// get no such rescue.
 #[must_use = "futures do nothing unless polled"]
 pub struct Delay {
     inner: Timer,
     delay: Duration,
+    deadline: Option<Instant>,
+    start: Option<Instant>,
     done: bool,
+    cancelled: bool,
+    // the time that was left when the delay was paused.
+    paused: Option<Duration>,
+    // when a poll first saw the delay complete.
+    fired_at: Option<Instant>,
 }
 
 impl Delay {
+    /// Creates a delay that fires `delay` after it is first polled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see [`Delay::try_new`] for a
+    /// version that returns the error instead.
     pub fn new(delay: Duration) -> Self {
-        let inner = Timer::new();
+        Delay::try_new(delay).expect("error creating native timer!")
+    }
+
+    /// Like [`Delay::new`], but returns the os error if the native timer can't be
+    /// created.
+    pub fn try_new(delay: Duration) -> io::Result<Self> {
+        Delay::try_with_clock(delay, Clock::default())
+    }
+
+    /// Creates a delay that fires `delay` from now, arming the native timer straight
+    /// away instead of on the first poll.
+    ///
+    /// Time spent before the delay is first polled counts against it, so one that is
+    /// awaited late fires that much sooner - or straight away, if it has already
+    /// fired.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created.
+    pub fn new_armed(delay: Duration) -> Self {
+        let mut this = Delay::new(delay);
+        this.arm(delay);
+        this
+    }
+
+    /// Creates one delay for each of `delays`, in the same order - each one behaves
+    /// exactly like a delay from [`Delay::new`].
+    ///
+    /// The batch takes every idle timer it can from this thread's pool in one go, so a
+    /// batch created after an earlier one was dropped on the same thread creates no new
+    /// os timers at all. The os has no call for creating several timers at once, so
+    /// each delay the pool can't cover still creates one of its own.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a native timer can't be created - see [`Delay::try_new_batch`] for a
+    /// version that returns the error instead.
+    pub fn new_batch(delays: &[Duration]) -> Vec<Self> {
+        Delay::try_new_batch(delays).expect("error creating native timer!")
+    }
+
+    /// Like [`Delay::new_batch`], but returns the os error if a native timer can't be
+    /// created. The timers that were already created go back to the pool.
+    pub fn try_new_batch(delays: &[Duration]) -> io::Result<Vec<Self>> {
+        let timers = Timer::try_new_batch(Clock::default(), delays.len())?;
+
+        Ok(timers
+            .into_iter()
+            .zip(delays)
+            .map(|(timer, &delay)| Delay::from_timer(timer, delay, None))
+            .collect())
+    }
+
+    /// Creates a delay that fires `delay` after it is first polled, measured against
+    /// `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see
+    /// [`Delay::try_with_clock`] for a version that returns the error instead.
+    pub fn with_clock(delay: Duration, clock: Clock) -> Self {
+        Delay::try_with_clock(delay, clock).expect("error creating native timer!")
+    }
+
+    /// Like [`Delay::with_clock`], but returns the os error if the native timer can't
+    /// be created.
+    pub fn try_with_clock(delay: Duration, clock: Clock) -> io::Result<Self> {
+        Ok(Delay::from_timer(Timer::try_new(clock)?, delay, None))
+    }
+
+    /// Creates a delay that fires `delay` after it is first polled, allowing the system
+    /// to fire it up to `leeway` late so it can coalesce wakeups and save power.
+    ///
+    /// The leeway is passed to the native timer on macOS and Windows, and ignored on
+    /// Linux.
+    pub fn with_leeway(delay: Duration, leeway: Duration) -> Self {
+        let mut this = Delay::new(delay);
+        this.inner.set_leeway(leeway);
+        this
+    }
+
+    /// Creates a delay that fires `delay` after it is first polled, driven by `wheel`
+    /// instead of an os timer of its own.
+    ///
+    /// The delay fires on the first tick of the wheel after it is due.
+    #[cfg(feature = "wheel")]
+    pub fn new_on(wheel: &TimerWheel, delay: Duration) -> Self {
+        Delay::from_timer(Timer::on_wheel(wheel), delay, None)
+    }
+
+    /// Creates a delay that fires at `deadline`.
+    ///
+    /// The time left is only computed when the delay is first polled, so a delay built
+    /// ahead of time doesn't drift. A deadline that has already passed completes on the
+    /// first poll without arming a native timer.
+    pub fn at(deadline: Instant) -> Self {
+        Delay::from_timer(Timer::new(), until(deadline), Some(deadline))
+    }
+
+    /// Creates a delay that fires `offset` after `base`.
+    ///
+    /// Delays sharing a base fire in the exact order, and at the exact gaps, of their
+    /// offsets, no matter how much time passes between creating them. Like
+    /// [`Delay::at`], an offset that has already passed completes on the first poll.
+    pub fn at_base(base: &TimeBase, offset: Duration) -> Self {
+        Delay::at(base.at(offset))
+    }
 
+    /// Wraps an unarmed timer, firing at `deadline` if there is one and `delay` after
+    /// the first poll otherwise.
+    pub(crate) fn from_timer(inner: Timer, delay: Duration, deadline: Option<Instant>) -> Self {
         Delay {
             inner,
             delay,
+            deadline,
+            start: None,
             done: false,
+            cancelled: false,
+            paused: None,
+            fired_at: None,
         }
     }
-}
 
-impl Future for Delay {
-    type Output = ();
+    /// Resets this delay to fire `delay` from now, re-arming the existing native timer.
+    ///
+    /// This works whether or not the delay has already fired, and the delay can be
+    /// polled to completion again afterwards. An expiry of the old deadline that the os
+    /// timer only delivers after the reset doesn't count towards the new one.
+    pub fn reset(&mut self, delay: Duration) {
+        self.delay = delay;
+        self.deadline = None;
+        self.done = false;
+        self.cancelled = false;
+        self.paused = None;
+        self.fired_at = None;
+        self.inner.state.clear();
+        self.arm(delay);
+    }
 
-    fn poll(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+    /// Disarms the native timer without dropping the delay.
+    ///
+    /// A cancelled delay stays pending until it is re-armed with [`Delay::reset`],
+    /// even if it had already fired.
+    pub fn cancel(&mut self) {
+        self.inner.disarm();
+        self.inner.state.clear();
+        self.start = None;
+        self.done = false;
+        self.cancelled = true;
+        self.paused = None;
+        self.fired_at = None;
+    }
+
+    /// Freezes the countdown, disarming the native timer until [`Delay::resume`].
+    ///
+    /// A paused delay stays pending, and its task isn't woken. This does nothing if the
+    /// delay has already fired, was cancelled, or is already paused.
+    pub fn pause(&mut self) {
+        if self.cancelled || self.paused.is_some() || self.is_elapsed() {
+            return;
+        }
+
+        let remaining = self.remaining();
+        self.inner.disarm();
+        self.inner.state.clear();
+        self.start = None;
+        self.paused = Some(remaining);
+    }
+
+    /// Restarts the countdown of a paused delay, re-arming the native timer with the
+    /// time that was left when it was paused.
+    ///
+    /// This does nothing if the delay isn't paused.
+    pub fn resume(&mut self) {
+        if let Some(remaining) = self.paused.take() {
+            self.delay = remaining;
+            self.deadline = None;
+            self.arm(remaining);
+        }
+    }
+
+    /// Returns whether the delay is paused.
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some()
+    }
+
+    /// Returns how much time is left before this delay fires.
+    ///
+    /// Before the first poll this is the full configured delay, and once the delay has
+    /// fired it is zero.
+    pub fn remaining(&self) -> Duration {
+        if self.inner.is_done() {
+            return Duration::from_secs(0);
+        }
+
+        if let Some(remaining) = self.paused {
+            return remaining;
+        }
+
+        match (self.start, self.deadline) {
+            (Some(start), _) => match start.checked_add(self.delay) {
+                Some(due) => until(due),
+                // due too far out to be an `Instant` - count down from the full delay.
+                None => self
+                    .delay
+                    .checked_sub(start.elapsed())
+                    .unwrap_or_else(|| Duration::from_secs(0)),
+            },
+            (None, Some(deadline)) => until(deadline),
+            (None, None) => self.delay,
+        }
+    }
+
+    /// Returns whether the native timer has fired, without polling the delay.
+    ///
+    /// This is `false` until the delay has been armed by its first poll.
+    pub fn is_elapsed(&self) -> bool {
+        self.done || self.inner.is_done()
+    }
+
+    /// Returns when the delay was seen to fire - by the poll that completed it - or
+    /// `None` if it hasn't completed yet.
+    ///
+    /// Compared with when the delay was due, this shows how late the timer (and the
+    /// task polling it) ran.
+    pub fn fired_at(&self) -> Option<Instant> {
+        self.fired_at
+    }
+
+    /// Returns whether the native timer is currently armed.
+    ///
+    /// A delay only arms its timer when it is first polled, so this is `false` until
+    /// then - and stays `false` for a delay with nothing to wait for, which completes
+    /// on its first poll instead.
+    pub fn is_active(&self) -> bool {
+        self.inner.is_active()
+    }
+
+    /// Creates a delay like [`Delay::new`], that resolves to `value` once it fires -
+    /// so that out of many delays, say in a `FuturesUnordered`, the one that fired can
+    /// tell itself apart.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created.
+    pub fn with_value<T>(delay: Duration, value: T) -> DelayWith<T> {
+        DelayWith {
+            delay: Delay::new(delay),
+            value: Some(value),
+        }
+    }
+
+    /// Turns this delay into an interval with the same period, reusing its native
+    /// timer rather than creating a new one.
+    ///
+    /// A delay that is still counting down has the interval's first tick land when the
+    /// delay would have fired. Otherwise - before the first poll, or once the delay has
+    /// completed - the first tick is a period after the interval is first polled.
+    pub fn repeat(mut self) -> Interval {
+        let first = if self.inner.is_active() && !self.is_elapsed() {
+            Some(self.deadline())
+        } else {
+            None
+        };
+
+        self.inner.disarm();
+        self.inner.state.clear();
+        Interval::from_timer(self.inner, self.delay, first)
+    }
+
+    /// Turns this delay into a stream that yields a single `()` once it fires, and
+    /// then ends.
+    ///
+    /// This is for code that works on streams, like `select_all` over several kinds of
+    /// timer - awaiting the delay itself is the usual way to use it.
+    pub fn into_stream(self) -> impl Stream<Item = ()> + Unpin {
+        stream::once(self)
+    }
+
+    /// Returns the os timer behind this delay - a `timer_t` on Linux (or a `timerfd`
+    /// with the `timerfd` feature), a `dispatch_source_t` on macOS, a `PTP_TIMER` on
+    /// Windows, the kqueue `ident` on the BSDs, the `setTimeout` id in the browser, and
+    /// the driving thread with the `fallback` backend.
+    ///
+    /// Returns `None` for timers driven by a `TimerWheel`, which don't have one.
+    ///
+    /// # Safety
+    ///
+    /// The handle is still owned by this delay, and is handed on to a new timer once it
+    /// is dropped. Anything done with it - especially re-arming, disarming or
+    /// deleting it - is the caller's responsibility, and can break the delay.
+    pub unsafe fn raw_handle(&self) -> Option<RawTimer> {
+        self.inner.handle.raw()
+    }
+
+    /// The configured duration of the delay.
+    pub(crate) fn duration(&self) -> Duration {
+        self.delay
+    }
+
+    /// When the delay is due - counting from now for a delay that hasn't been armed
+    /// yet, or is paused. One due too far out for an `Instant` gets the latest one there
+    /// is.
+    pub(crate) fn deadline(&self) -> Instant {
+        if let Some(remaining) = self.paused {
+            return saturating_add(Instant::now(), remaining);
+        }
+
+        match (self.start, self.deadline) {
+            (Some(start), _) => saturating_add(start, self.delay),
+            (None, Some(deadline)) => deadline,
+            (None, None) => saturating_add(Instant::now(), self.delay),
+        }
+    }
+
+    /// How long it has been since the delay was armed.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.start
+            .map(|start| start.elapsed())
+            .unwrap_or_else(|| Duration::from_secs(0))
+    }
+
+    /// Polls just the timer, for futures that embed a delay and poll it by hand.
+    ///
+    /// This is what `Future::poll` does for a delay - the first call arms the native
+    /// timer, and `lw` is woken once it fires. Once the delay has completed, every
+    /// later call returns `Ready` straight away, without touching the timer, until the
+    /// delay is reset.
+    pub fn poll_elapsed(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<()> {
+        if self.cancelled || self.paused.is_some() {
+            return Poll::Pending;
+        }
+
+        // never re-arm a completed delay, whatever the timer says about itself.
+        if self.done {
+            return Poll::Ready(());
+        }
+
+        // register before arming, so that a timer firing straight away still has a
+        // waker to wake.
+        self.inner.register_waker(lw);
         if !self.inner.is_active() {
+            if let Some(deadline) = self.deadline {
+                self.delay = until(deadline);
+            }
+
+            // a zero delay, or a deadline that has already passed - there is nothing to
+            // wait for, so nothing to arm.
+            if self.delay == Duration::from_secs(0) {
+                self.complete();
+                return Poll::Ready(());
+            }
+
             let delay = self.delay;
-            self.inner.handle.init_delay(delay);
+            self.arm(delay);
         }
 
-        self.inner.register_waker(lw);
         if self.inner.is_done() {
-            self.done = true;
-            Poll::Ready(())
-        } else {
-            Poll::Pending
+            self.complete();
+            return Poll::Ready(());
         }
+
+        Poll::Pending
+    }
+
+    fn complete(&mut self) {
+        self.done = true;
+
+        // taken here rather than in the timer callback, which should do no more than it
+        // has to.
+        if self.fired_at.is_none() {
+            self.fired_at = Some(Instant::now());
+        }
+    }
+
+    fn arm(&mut self, delay: Duration) {
+        self.start = Some(Instant::now());
+        self.inner.init_delay(delay);
+    }
+}
+
+impl Future for Delay {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+        self.poll_elapsed(lw)
     }
 }
 
@@ -53,3 +487,769 @@ impl FusedFuture for Delay {
 }
 
 impl Unpin for Delay {}
+
+/// A delay that resolves to a value, created by [`Delay::with_value`].
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct DelayWith<T> {
+    delay: Delay,
+    // taken out by the poll that completes the delay.
+    value: Option<T>,
+}
+
+impl<T> DelayWith<T> {
+    /// Returns a reference to the underlying delay.
+    pub fn get_ref(&self) -> &Delay {
+        &self.delay
+    }
+
+    /// Returns a mutable reference to the underlying delay.
+    pub fn get_mut(&mut self) -> &mut Delay {
+        &mut self.delay
+    }
+
+    /// Returns the value, if the delay hasn't resolved to it yet.
+    pub fn value(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    /// Returns the value without waiting for the delay, if it hasn't resolved to it
+    /// yet.
+    pub fn into_value(self) -> Option<T> {
+        self.value
+    }
+}
+
+impl<T> Future for DelayWith<T> {
+    type Output = T;
+
+    /// # Panics
+    ///
+    /// Panics if polled again after it has resolved - the value has been handed out by
+    /// then.
+    fn poll(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+        assert!(self.value.is_some(), "`DelayWith` polled after completion");
+
+        match Pin::new(&mut self.delay).poll(lw) {
+            Poll::Ready(()) => Poll::Ready(self.value.take().unwrap()),
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl<T> FusedFuture for DelayWith<T> {
+    fn is_terminated(&self) -> bool {
+        self.value.is_none()
+    }
+}
+
+// the value is never pinned.
+impl<T> Unpin for DelayWith<T> {}
+
+/// A stream of the indices of a set of delays in the order they fire, created by
+/// [`join_ordered`].
+pub struct JoinOrdered {
+    delays: FuturesUnordered<DelayWith<usize>>,
+}
+
+impl JoinOrdered {
+    /// Returns how many of the delays haven't fired yet.
+    pub fn remaining(&self) -> usize {
+        self.delays.len()
+    }
+}
+
+impl Stream for JoinOrdered {
+    type Item = usize;
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.delays).poll_next(lw)
+    }
+}
+
+impl FusedStream for JoinOrdered {
+    fn is_terminated(&self) -> bool {
+        self.delays.is_empty()
+    }
+}
+
+impl Unpin for JoinOrdered {}
+
+impl fmt::Debug for JoinOrdered {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("JoinOrdered")
+            .field("remaining", &self.remaining())
+            .finish()
+    }
+}
+
+impl From<Duration> for Delay {
+    /// Same as [`Delay::new`].
+    fn from(delay: Duration) -> Self {
+        Delay::new(delay)
+    }
+}
+
+impl From<Instant> for Delay {
+    /// Same as [`Delay::at`].
+    fn from(deadline: Instant) -> Self {
+        Delay::at(deadline)
+    }
+}
+
+/// Creates a new delay with the same configuration, which starts out unarmed.
+///
+/// A clone gets a native timer of its own - driven the same way, with the same leeway -
+/// and none of the original's timing state: it fires `delay` after it is first polled,
+/// or at the original's deadline for one created with [`Delay::at`], however far along
+/// the original is and even if it has already completed.
+///
+/// # Panics
+///
+/// Panics if the native timer can't be created.
+impl Clone for Delay {
+    fn clone(&self) -> Self {
+        let inner = self
+            .inner
+            .try_clone()
+            .expect("error creating native timer!");
+        Delay::from_timer(inner, self.delay, self.deadline)
+    }
+}
+
+impl fmt::Debug for Delay {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Delay")
+            .field("delay", &self.delay)
+            .field("remaining", &self.remaining())
+            .field("armed", &self.inner.is_active())
+            .field("done", &self.is_elapsed())
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockTimer, TimerSource};
+    use futures::executor::block_on;
+    use futures::task::noop_waker_ref;
+    use std::thread;
+
+    #[test]
+    fn delay_batch() {
+        use crate::TimerState;
+        use std::sync::Arc;
+
+        fn reused(batch: &[Delay], states: &[Arc<TimerState>]) -> usize {
+            batch
+                .iter()
+                .filter(|delay| states.iter().any(|s| Arc::ptr_eq(s, &delay.inner.state)))
+                .count()
+        }
+
+        // on a thread of its own, so its pool starts out empty.
+        thread::spawn(|| {
+            let durations: Vec<_> = (1..=20).map(|i| Duration::from_millis(i * 5)).collect();
+
+            let first = Delay::new_batch(&durations);
+            assert_eq!(first.len(), durations.len());
+            for (delay, &duration) in first.iter().zip(durations.iter()) {
+                assert_eq!(delay.duration(), duration);
+            }
+
+            let states: Vec<_> = first.iter().map(|d| d.inner.state.clone()).collect();
+            drop(first);
+
+            // every timer of the second batch comes from the pool, so none are created.
+            let second = Delay::new_batch(&durations);
+            assert_eq!(reused(&second, &states), durations.len());
+
+            let start = Instant::now();
+            for delay in second {
+                block_on(delay);
+            }
+            assert!(start.elapsed() >= Duration::from_millis(100));
+
+            // a bigger batch only creates the timers the pool couldn't hand out.
+            let third = Delay::new_batch(&[Duration::from_secs(60); 50]);
+            assert_eq!(third.len(), 50);
+            assert_eq!(reused(&third, &states), durations.len());
+        })
+        .join()
+        .unwrap();
+    }
+
+    #[test]
+    fn reset_drops_stale_expiry() {
+        let mut delay = Delay::new(Duration::from_secs(3600));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        let generation = delay.inner.state.generation();
+        delay.reset(Duration::from_secs(3600));
+
+        // the old deadline's expiry, only delivered after the reset.
+        delay.inner.state.fire_for(generation, 1);
+        delay.inner.state.fire_expired(Duration::from_secs(0));
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(!delay.is_elapsed());
+    }
+
+    #[test]
+    fn remaining_of_huge_delay() {
+        let huge = Duration::from_secs(u64::max_value());
+        let mut delay = Delay::new(huge);
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        let remaining = delay.remaining();
+        assert!(remaining < huge);
+        assert!(remaining > huge - Duration::from_secs(60));
+
+        // nothing that reports on the delay panics either.
+        assert!(delay.deadline() > Instant::now() + Duration::from_secs(3600));
+        assert!(format!("{:?}", delay).contains("remaining"));
+
+        let paused = Delay::new(huge);
+        assert!(paused.deadline() > Instant::now() + Duration::from_secs(3600));
+    }
+
+    #[test]
+    fn huge_delay() {
+        let mut delay = Delay::new(Duration::from_secs(u64::max_value()));
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        thread::sleep(Duration::from_millis(50));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(!delay.is_elapsed());
+
+        // reporting on it doesn't overflow, even though its deadline can't be an
+        // `Instant`.
+        assert!(delay.remaining() > Duration::from_secs(u64::max_value() / 2));
+        assert!(format!("{:?}", delay).starts_with("Delay"));
+    }
+
+    #[test]
+    fn subsecond_delay() {
+        let start = Instant::now();
+        block_on(Delay::new(Duration::from_millis(250)));
+
+        // not rounded up (or down) to a whole second.
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(250));
+        assert!(elapsed < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn reset_delay() {
+        let mut delay = Delay::new(Duration::from_secs(5));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        let work = async {
+            let t = Instant::now();
+
+            // re-arm the delay while the original timer is still pending
+            delay.reset(Duration::from_millis(100));
+            await!(&mut delay);
+            assert!(delay.is_terminated());
+
+            // and again once it has already fired
+            delay.reset(Duration::from_millis(100));
+            assert!(!delay.is_terminated());
+            await!(&mut delay);
+
+            t.elapsed()
+        };
+
+        let res = block_on(work);
+        assert!(res >= Duration::from_millis(200));
+        assert!(res < Duration::from_secs(1));
+    }
+
+    #[test]
+    fn reset_delay_on_mock() {
+        let mock = MockTimer::new();
+        let mut delay = mock.delay(Duration::from_secs(5));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        // re-arm the delay while the original timer is still pending
+        delay.reset(Duration::from_millis(100));
+        mock.advance(Duration::from_millis(99));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        mock.advance(Duration::from_millis(1));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+        assert!(delay.is_terminated());
+
+        // and again once it has already fired
+        delay.reset(Duration::from_millis(100));
+        assert!(!delay.is_terminated());
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        mock.advance(Duration::from_millis(100));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+
+        // the original deadline is long gone.
+        delay.reset(Duration::from_secs(60));
+        mock.advance(Duration::from_secs(5));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+    }
+
+    #[test]
+    fn delay_repeat() {
+        let start = Instant::now();
+        let mut delay = Delay::new(Duration::from_millis(20));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        // the first tick is when the delay was due, and the rest keep its period.
+        let interval = delay.repeat();
+        assert_eq!(interval.period(), Duration::from_millis(20));
+
+        let ticks = block_on(interval.take(3).collect::<Vec<_>>());
+        assert!(ticks[0] >= start + Duration::from_millis(20));
+        assert!(ticks[0] < start + Duration::from_millis(35));
+        for pair in ticks.windows(2) {
+            let gap = pair[1] - pair[0];
+            assert!(gap >= Duration::from_millis(10) && gap < Duration::from_millis(30));
+        }
+
+        // and back again.
+        let start = Instant::now();
+        let delay = Interval::new(Duration::from_millis(20)).once();
+        assert_eq!(delay.remaining(), Duration::from_millis(20));
+        block_on(delay);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn delay_with_value() {
+        use futures::stream::FuturesUnordered;
+
+        let delays: FuturesUnordered<_> = [30, 10, 20]
+            .iter()
+            .map(|&ms| Delay::with_value(Duration::from_millis(ms), ms))
+            .collect();
+
+        let order = block_on(delays.collect::<Vec<_>>());
+        assert_eq!(order, vec![10, 20, 30]);
+
+        let delay = Delay::with_value(Duration::from_secs(5), "payload");
+        assert_eq!(delay.value(), Some(&"payload"));
+        assert_eq!(delay.into_value(), Some("payload"));
+    }
+
+    #[test]
+    fn join_ordered_delays() {
+        let delays = [30, 10, 20]
+            .iter()
+            .map(|&ms| Delay::new(Duration::from_millis(ms)))
+            .collect();
+
+        let order = block_on(join_ordered(delays).collect::<Vec<_>>());
+        assert_eq!(order, vec![1, 2, 0]);
+
+        let empty = join_ordered(Vec::new());
+        assert_eq!(empty.remaining(), 0);
+        assert_eq!(block_on(empty.collect::<Vec<_>>()), vec![]);
+    }
+
+    #[test]
+    fn poll_after_completion() {
+        use futures::select;
+
+        let mut delay = Delay::new(Duration::from_millis(10));
+        block_on(&mut delay);
+        let fired_at = delay.fired_at();
+
+        for _ in 0..3 {
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+            assert!(delay.is_terminated());
+            assert_eq!(delay.fired_at(), fired_at);
+        }
+
+        // the same goes for a delay that completed without arming its timer.
+        let mut zero = Delay::new(Duration::from_secs(0));
+        for _ in 0..3 {
+            assert!(Pin::new(&mut zero).poll(noop_waker_ref()).is_ready());
+            assert!(zero.is_terminated());
+            assert!(!zero.is_active());
+        }
+
+        // `select!` skips the completed delays, instead of taking them again.
+        let mut other = Delay::new(Duration::from_millis(10));
+        let res = block_on(async {
+            select! {
+                _ = delay => "completed delay",
+                _ = zero => "completed zero delay",
+                _ = other => "other delay",
+            }
+        });
+        assert_eq!(res, "other delay");
+    }
+
+    #[test]
+    fn reuse_delay() {
+        let mut delay = Delay::new(Duration::from_millis(30));
+        let mut completions = 0;
+
+        let work = async {
+            let t = Instant::now();
+
+            for _ in 0..3 {
+                await!(&mut delay);
+                completions += 1;
+
+                // a completed delay stays completed until it's reset.
+                assert!(delay.is_terminated());
+                assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+
+                delay.reset(Duration::from_millis(30));
+            }
+
+            t.elapsed()
+        };
+
+        let res = block_on(work);
+        assert_eq!(completions, 3);
+        assert!(res >= Duration::from_millis(90));
+    }
+
+    #[test]
+    fn reuse_delay_on_mock() {
+        let mock = MockTimer::new();
+        let mut delay = mock.delay(Duration::from_millis(30));
+
+        for _ in 0..3 {
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            mock.advance(Duration::from_millis(30));
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+
+            // a completed delay stays completed until it's reset.
+            assert!(delay.is_terminated());
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+
+            delay.reset(Duration::from_millis(30));
+        }
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+    }
+
+    #[cfg(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    ))]
+    #[test]
+    fn raw_handle() {
+        extern "C" {
+            fn timer_gettime(timerid: RawTimer, curr_value: *mut libc::itimerspec) -> libc::c_int;
+        }
+
+        let mut delay = Delay::new(Duration::from_secs(5));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        let raw = unsafe { delay.raw_handle() }.unwrap();
+        let mut spec: libc::itimerspec = unsafe { std::mem::zeroed() };
+        assert_eq!(unsafe { timer_gettime(raw, &mut spec) }, 0);
+
+        // armed, and counting down from 5s.
+        assert!(spec.it_value.tv_sec > 0 || spec.it_value.tv_nsec > 0);
+        assert!(spec.it_value.tv_sec < 5);
+    }
+
+    #[test]
+    fn cancel_delay() {
+        let mut delay = Delay::new(Duration::from_millis(50));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        delay.cancel();
+
+        let start = Instant::now();
+        while start.elapsed() < Duration::from_millis(100) {
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        assert!(!delay.is_elapsed());
+
+        // and it can still be re-armed afterwards.
+        delay.reset(Duration::from_millis(20));
+        block_on(&mut delay);
+        assert!(delay.is_elapsed());
+    }
+
+    #[test]
+    fn cancel_delay_on_mock() {
+        let mock = MockTimer::new();
+        let mut delay = mock.delay(Duration::from_millis(50));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        delay.cancel();
+
+        // a cancelled delay never fires, however long it waits.
+        mock.advance(Duration::from_secs(3600));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(!delay.is_elapsed());
+
+        // and it can still be re-armed afterwards.
+        delay.reset(Duration::from_millis(20));
+        mock.advance(Duration::from_millis(20));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+        assert!(delay.is_elapsed());
+    }
+
+    #[test]
+    fn poll_elapsed() {
+        use futures::task::Poll;
+
+        // a hand-written future that only needs the timer part of a delay.
+        struct Polled {
+            delay: Delay,
+            polls: usize,
+        }
+
+        impl Future for Polled {
+            type Output = usize;
+
+            fn poll(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<usize> {
+                self.polls += 1;
+
+                match Pin::new(&mut self.delay).poll_elapsed(lw) {
+                    Poll::Ready(()) => Poll::Ready(self.polls),
+                    Poll::Pending => Poll::Pending,
+                }
+            }
+        }
+
+        let polled = Polled {
+            delay: Delay::new(Duration::from_millis(20)),
+            polls: 0,
+        };
+
+        assert!(block_on(polled) >= 2);
+    }
+
+    #[test]
+    fn delay_from() {
+        let delay: Delay = Duration::from_secs(2).into();
+        assert_eq!(delay.duration(), Duration::from_secs(2));
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+        let delay = Delay::from(deadline);
+        assert!(delay.remaining() <= Duration::from_millis(50));
+
+        block_on(delay);
+        assert!(Instant::now() >= deadline);
+    }
+
+    #[test]
+    fn pause_delay() {
+        let start = Instant::now();
+        let mut delay = Delay::new(Duration::from_millis(300));
+        let waker = noop_waker_ref();
+        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+
+        thread::sleep(Duration::from_millis(100));
+        delay.pause();
+        assert!(delay.is_paused());
+
+        // frozen - with the timer disarmed, it can't fire while paused.
+        let remaining = delay.remaining();
+        assert!(remaining <= Duration::from_millis(200));
+        thread::sleep(Duration::from_millis(500));
+        assert_eq!(delay.remaining(), remaining);
+        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+        assert!(!delay.is_elapsed());
+
+        delay.resume();
+        assert!(!delay.is_paused());
+        block_on(delay);
+
+        // the 500ms spent paused is added on top of the delay.
+        assert!(start.elapsed() >= Duration::from_millis(800));
+    }
+
+    #[test]
+    fn pause_delay_on_mock() {
+        let mock = MockTimer::new();
+        let mut delay = mock.delay(Duration::from_millis(300));
+        let waker = noop_waker_ref();
+        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+
+        delay.pause();
+        assert!(delay.is_paused());
+
+        // frozen - with the timer disarmed, it can't fire while paused.
+        let remaining = delay.remaining();
+        assert!(remaining <= Duration::from_millis(300));
+        mock.advance(Duration::from_secs(3600));
+        assert_eq!(delay.remaining(), remaining);
+        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+        assert!(!delay.is_elapsed());
+
+        // the countdown picks up where it was paused.
+        delay.resume();
+        assert!(!delay.is_paused());
+        mock.advance(remaining - Duration::from_millis(1));
+        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+        mock.advance(Duration::from_millis(1));
+        assert!(Pin::new(&mut delay).poll(waker).is_ready());
+    }
+
+    #[test]
+    fn armed_delay() {
+        let lazy = Delay::new(Duration::from_millis(100));
+        let armed = Delay::new_armed(Duration::from_millis(100));
+        assert!(armed.remaining() <= Duration::from_millis(100));
+
+        thread::sleep(Duration::from_millis(60));
+
+        // only the armed delay has been counting down.
+        let start = Instant::now();
+        block_on(armed);
+        let armed_wait = start.elapsed();
+
+        let start = Instant::now();
+        block_on(lazy);
+        let lazy_wait = start.elapsed();
+
+        assert!(armed_wait < Duration::from_millis(60));
+        assert!(lazy_wait >= Duration::from_millis(100));
+    }
+
+    #[test]
+    fn delay_into_stream() {
+        let start = Instant::now();
+        let ticks: Vec<()> = block_on(
+            Delay::new(Duration::from_millis(50))
+                .into_stream()
+                .collect(),
+        );
+        assert_eq!(ticks.len(), 1);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // merged with another, each delay still ticks just once.
+        let fast = Delay::new(Duration::from_millis(10)).into_stream();
+        let slow = Delay::new(Duration::from_millis(30)).into_stream();
+        let ticks: Vec<()> = block_on(fast.select(slow).collect());
+        assert_eq!(ticks.len(), 2);
+    }
+
+    #[test]
+    fn delay_at_deadline() {
+        let start = Instant::now();
+        let delay = Delay::at(start + Duration::from_millis(50));
+
+        block_on(delay);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(50));
+        assert!(elapsed < Duration::from_millis(500));
+
+        // a deadline in the past is ready straight away
+        let mut past = Delay::at(start);
+        assert!(Pin::new(&mut past).poll(noop_waker_ref()).is_ready());
+        assert!(!past.is_active());
+    }
+
+    #[test]
+    fn clone_delay() {
+        let mut delay = Delay::new(Duration::from_millis(20));
+        block_on(&mut delay);
+
+        // the clone starts over, rather than sharing the finished timer.
+        let mut clone = delay.clone();
+        assert!(!clone.is_active());
+        assert!(!clone.is_elapsed());
+        let start = Instant::now();
+        block_on(&mut clone);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // and is driven the same way as the original.
+        let mock = MockTimer::new();
+        let delay = mock.delay(Duration::from_secs(60));
+        let mut clone = delay.clone();
+        assert!(Pin::new(&mut clone).poll(noop_waker_ref()).is_pending());
+        mock.advance(Duration::from_secs(60));
+        assert!(Pin::new(&mut clone).poll(noop_waker_ref()).is_ready());
+    }
+
+    #[test]
+    fn delay_fired_at() {
+        let mut delay = Delay::new(Duration::from_millis(50));
+        assert_eq!(delay.fired_at(), None);
+
+        let start = Instant::now();
+        block_on(&mut delay);
+        let fired_at = delay.fired_at().unwrap();
+        assert!(fired_at >= start + Duration::from_millis(50));
+        assert!(fired_at <= Instant::now());
+
+        // stays put on later polls, and is cleared by a reset.
+        block_on(&mut delay);
+        assert_eq!(delay.fired_at(), Some(fired_at));
+
+        delay.reset(Duration::from_millis(10));
+        assert_eq!(delay.fired_at(), None);
+    }
+
+    #[test]
+    fn zero_delay() {
+        // completes on the first poll, without arming the native timer.
+        let mut delay = Delay::new(Duration::from_secs(0));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+        assert!(!delay.is_active());
+        assert!(delay.is_terminated());
+
+        let mut delay = Delay::new(Duration::from_millis(20));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(delay.is_active());
+    }
+
+    #[test]
+    fn delay_remaining() {
+        let mut delay = Delay::new(Duration::from_millis(200));
+        assert_eq!(delay.remaining(), Duration::from_millis(200));
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        let remaining = delay.remaining();
+        assert!(remaining > Duration::from_secs(0));
+        assert!(remaining <= Duration::from_millis(200));
+
+        block_on(&mut delay);
+        assert_eq!(delay.remaining(), Duration::from_secs(0));
+    }
+
+    #[test]
+    fn delay_is_elapsed() {
+        let mut delay = Delay::new(Duration::from_millis(50));
+        assert!(!delay.is_elapsed());
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(!delay.is_elapsed());
+
+        thread::sleep(Duration::from_millis(200));
+        assert!(delay.is_elapsed());
+    }
+
+    #[test]
+    fn delay_is_elapsed_on_mock() {
+        let mock = MockTimer::new();
+        let mut delay = mock.delay(Duration::from_millis(50));
+        assert!(!delay.is_elapsed());
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(!delay.is_elapsed());
+
+        // without being polled again.
+        mock.advance(Duration::from_millis(50));
+        assert!(delay.is_elapsed());
+    }
+
+    #[test]
+    fn sleep_helpers() {
+        let start = Instant::now();
+
+        block_on(sleep(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        block_on(sleep_until(start + Duration::from_millis(50)));
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+}
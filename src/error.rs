@@ -0,0 +1,128 @@
+use std::{error, fmt, io};
+
+use crate::TimeoutError;
+
+/// Any error from this crate, for code that wants one error type to pass on with `?`.
+///
+/// The operations themselves keep returning their own, more specific errors - a
+/// `Timeout` yields a [`TimeoutError`], and the fallible constructors an `io::Error` -
+/// which all convert into this.
+#[derive(Debug)]
+pub enum Error {
+    /// A timeout fired before the future it was guarding finished.
+    TimedOut(TimeoutError),
+    /// The os failed to create or arm a timer. This may hold a [`TimerError`].
+    Os(io::Error),
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match self {
+            Error::TimedOut(err) => Some(err),
+            Error::Os(err) => Some(err),
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::TimedOut(err) => err.fmt(f),
+            Error::Os(err) => write!(f, "error creating native timer: {}", err),
+        }
+    }
+}
+
+impl From<TimeoutError> for Error {
+    fn from(err: TimeoutError) -> Self {
+        Error::TimedOut(err)
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Os(err)
+    }
+}
+
+/// Errors from creating a native timer that this crate tells apart from other os
+/// errors.
+///
+/// These come wrapped in the `io::Error` returned by the fallible constructors, like
+/// [`Delay::try_new`] - use [`TimerError::of`] to get them back out.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimerError {
+    /// The process has as many os timers as the system will give it.
+    ///
+    /// On Linux, posix timers count against `RLIMIT_SIGPENDING`. With the `wheel`
+    /// feature, a wheel set with [`TimerWheel::use_for_overflow`] takes the timers
+    /// that would fail like this instead.
+    LimitReached,
+}
+
+impl TimerError {
+    /// Returns the `TimerError` inside `err`, if it is one.
+    pub fn of(err: &io::Error) -> Option<TimerError> {
+        err.get_ref()
+            .and_then(|inner| inner.downcast_ref::<TimerError>())
+            .cloned()
+    }
+}
+
+impl error::Error for TimerError {}
+
+impl fmt::Display for TimerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TimerError::LimitReached => write!(f, "the process can't create any more timers"),
+        }
+    }
+}
+
+impl From<TimerError> for io::Error {
+    fn from(err: TimerError) -> Self {
+        io::Error::new(io::ErrorKind::Other, err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Delay, FutureExt as _};
+    use futures::executor::block_on;
+    use std::time::Duration;
+
+    #[test]
+    fn timer_error() {
+        let err: io::Error = TimerError::LimitReached.into();
+        assert_eq!(TimerError::of(&err), Some(TimerError::LimitReached));
+
+        // other os errors aren't mistaken for one - not even a bare `EAGAIN`.
+        let err = io::Error::from_raw_os_error(11);
+        assert_eq!(TimerError::of(&err), None);
+    }
+
+    #[test]
+    fn crate_error() {
+        use futures::future::empty;
+
+        fn wait() -> Result<(), Error> {
+            let delay = Delay::try_new(Duration::from_millis(10))?;
+            block_on(delay);
+
+            block_on(empty::<()>().timeout(Duration::from_millis(10)))?;
+            Ok(())
+        }
+
+        match wait() {
+            Err(Error::TimedOut(err)) => assert_eq!(err.timeout(), Duration::from_millis(10)),
+            res => panic!("expected a timeout, got {:?}", res),
+        }
+
+        let err = Error::from(io::Error::from(TimerError::LimitReached));
+        match err {
+            Error::Os(ref err) => assert_eq!(TimerError::of(err), Some(TimerError::LimitReached)),
+            _ => panic!("expected an os error"),
+        }
+    }
+}
@@ -0,0 +1,175 @@
+use std::fmt;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::future::FusedFuture;
+use futures::prelude::*;
+use futures::task::{Poll, Waker};
+
+use super::Delay;
+
+/// A future that completes once nothing has touched it for a whole `duration`.
+///
+/// Every [`IdleBarrier::touch`] - or [`IdleHandle::touch`], from any thread - pushes
+/// the barrier back to a full duration from then, so awaiting it waits out the
+/// activity, e.g. to shut a server down once it has gone quiet. The barrier counts
+/// from its creation, so one that is never touched completes `duration` after it was
+/// created.
+///
+/// Once the barrier has completed it stays completed, and touching it does nothing.
+pub struct IdleBarrier {
+    inner: Arc<Mutex<Idle>>,
+}
+
+/// A handle for touching an [`IdleBarrier`] from elsewhere, created by
+/// [`IdleBarrier::handle`].
+///
+/// Handles can be cloned and sent to other threads, and touching one after the barrier
+/// is gone does nothing.
+#[derive(Clone)]
+pub struct IdleHandle {
+    inner: Arc<Mutex<Idle>>,
+}
+
+struct Idle {
+    // re-armed by every touch, rather than creating a timer each time.
+    delay: Delay,
+    duration: Duration,
+    done: bool,
+}
+
+impl Idle {
+    fn touch(&mut self) {
+        if !self.done {
+            self.delay.reset(self.duration);
+        }
+    }
+}
+
+impl IdleBarrier {
+    /// Creates a barrier that completes once `duration` passes without a touch.
+    pub fn new(duration: Duration) -> Self {
+        let idle = Idle {
+            delay: Delay::new_armed(duration),
+            duration,
+            done: false,
+        };
+
+        IdleBarrier {
+            inner: Arc::new(Mutex::new(idle)),
+        }
+    }
+
+    /// Pushes the barrier back to a full duration from now.
+    pub fn touch(&self) {
+        self.inner.lock().unwrap().touch();
+    }
+
+    /// Returns a handle that touches this barrier.
+    pub fn handle(&self) -> IdleHandle {
+        IdleHandle {
+            inner: self.inner.clone(),
+        }
+    }
+
+    /// Returns the quiet period the barrier waits for.
+    pub fn duration(&self) -> Duration {
+        self.inner.lock().unwrap().duration
+    }
+}
+
+impl IdleHandle {
+    /// Pushes the barrier back to a full duration from now.
+    pub fn touch(&self) {
+        self.inner.lock().unwrap().touch();
+    }
+}
+
+impl Future for IdleBarrier {
+    type Output = ();
+
+    fn poll(self: Pin<&mut Self>, lw: &Waker) -> Poll<Self::Output> {
+        let mut idle = self.inner.lock().unwrap();
+        if idle.done {
+            return Poll::Ready(());
+        }
+
+        // a touch only ever moves the delay later, so once it has fired there was no
+        // touch in the last `duration`.
+        match Pin::new(&mut idle.delay).poll(lw) {
+            Poll::Ready(()) => {
+                idle.done = true;
+                Poll::Ready(())
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+impl FusedFuture for IdleBarrier {
+    fn is_terminated(&self) -> bool {
+        self.inner.lock().unwrap().done
+    }
+}
+
+impl Unpin for IdleBarrier {}
+
+impl fmt::Debug for IdleBarrier {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let idle = self.inner.lock().unwrap();
+        f.debug_struct("IdleBarrier")
+            .field("duration", &idle.duration)
+            .field("done", &idle.done)
+            .finish()
+    }
+}
+
+impl fmt::Debug for IdleHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IdleHandle").finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use std::time::Instant;
+
+    #[test]
+    fn idle_barrier() {
+        use std::thread;
+
+        let start = Instant::now();
+        let barrier = IdleBarrier::new(Duration::from_millis(50));
+
+        // four threads keep the barrier busy for around 200ms between them.
+        let touchers: Vec<_> = (0..4)
+            .map(|_| {
+                let handle = barrier.handle();
+                thread::spawn(move || {
+                    while start.elapsed() < Duration::from_millis(200) {
+                        handle.touch();
+                        thread::sleep(Duration::from_millis(10));
+                    }
+                })
+            })
+            .collect();
+        barrier.touch();
+
+        block_on(barrier);
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(200));
+        assert!(elapsed < Duration::from_millis(1000));
+
+        for toucher in touchers {
+            toucher.join().unwrap();
+        }
+
+        // an untouched barrier completes a whole duration after its creation.
+        let start = Instant::now();
+        block_on(IdleBarrier::new(Duration::from_millis(30)));
+        assert!(start.elapsed() >= Duration::from_millis(30));
+    }
+}
@@ -1,23 +1,429 @@
+use std::fmt;
+use std::io;
 use std::pin::Pin;
-use std::time::{Duration, Instant};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 use futures::prelude::*;
 use futures::stream::FusedStream;
 use futures::task::{Poll, Waker};
 
-use super::Timer;
+use super::{saturating_add, until, Clock, Delay, RawTimer, Timer};
+
+/// How an [`Interval`] catches up when it isn't polled for longer than its period.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum MissedTickBehavior {
+    /// Yield every missed tick, one per poll, until the interval has caught up.
+    Burst,
+    /// Yield a single tick for all the missed ones, and start the next period from
+    /// that poll.
+    Delay,
+    /// Yield a single tick for all the missed ones, keeping the original schedule.
+    ///
+    /// This is the default, and matches how the native timers deliver ticks.
+    Skip,
+}
+
+impl Default for MissedTickBehavior {
+    fn default() -> Self {
+        MissedTickBehavior::Skip
+    }
+}
 
-#[derive(Debug)]
 pub struct Interval {
     inner: Timer,
     interval: Duration,
+    start: Option<Instant>,
+    missed_tick_behavior: MissedTickBehavior,
+    missed_ticks: usize,
+    // how many ticks have been yielded, since creation or `reset_ticks`.
+    yielded: u64,
+    period_changed: bool,
+    jitter: Option<Jitter>,
+    terminated: bool,
+}
+
+/// Randomizes the ticks of a jittered interval - see [`Interval::with_jitter`].
+#[derive(Debug)]
+struct Jitter {
+    amount: Duration,
+    // xorshift64 state, which must never be zero.
+    rng: u64,
+    // when the pending tick would have happened without any jitter.
+    base: Instant,
+}
+
+impl Jitter {
+    fn new(amount: Duration, seed: u64) -> Self {
+        Jitter {
+            amount,
+            rng: seed | 1,
+            base: Instant::now(),
+        }
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.rng ^= self.rng << 13;
+        self.rng ^= self.rng >> 7;
+        self.rng ^= self.rng << 17;
+        self.rng
+    }
+
+    /// Returns `base` moved by a random amount within `±amount`.
+    fn perturb(&mut self, base: Instant) -> Instant {
+        let amount = self.amount.as_nanos().min(u128::from(u64::max_value() / 2)) as u64;
+        let offset = self.next_u64() % (2 * amount + 1);
+
+        // near the edges of what an `Instant` can hold, the tick stays on its base.
+        if offset < amount {
+            base.checked_sub(Duration::from_nanos(amount - offset))
+                .unwrap_or(base)
+        } else {
+            saturating_add(base, Duration::from_nanos(offset - amount))
+        }
+    }
 }
 
 impl Interval {
+    /// Creates an interval that ticks every `interval`, starting one period after it
+    /// is first polled.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see [`Interval::try_new`] for a
+    /// version that returns the error instead.
     pub fn new(interval: Duration) -> Self {
-        let inner = Timer::new();
+        Interval::try_new(interval).expect("error creating native timer!")
+    }
+
+    /// Like [`Interval::new`], but returns the os error if the native timer can't be
+    /// created.
+    pub fn try_new(interval: Duration) -> io::Result<Self> {
+        Interval::try_with_clock(interval, Clock::default())
+    }
+
+    /// Creates an interval that ticks every `interval`, measured against `clock`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see
+    /// [`Interval::try_with_clock`] for a version that returns the error instead.
+    pub fn with_clock(interval: Duration, clock: Clock) -> Self {
+        Interval::try_with_clock(interval, clock).expect("error creating native timer!")
+    }
+
+    /// Like [`Interval::with_clock`], but returns the os error if the native timer
+    /// can't be created.
+    pub fn try_with_clock(interval: Duration, clock: Clock) -> io::Result<Self> {
+        Ok(Interval::from_timer(Timer::try_new(clock)?, interval, None))
+    }
+
+    /// Wraps an unarmed timer, with the first tick at `start` if there is one and one
+    /// period after the first poll otherwise.
+    pub(crate) fn from_timer(inner: Timer, interval: Duration, start: Option<Instant>) -> Self {
+        Interval {
+            inner,
+            interval,
+            start,
+            missed_tick_behavior: MissedTickBehavior::default(),
+            missed_ticks: 0,
+            yielded: 0,
+            period_changed: false,
+            jitter: None,
+            terminated: false,
+        }
+    }
+
+    /// Creates an interval that ticks every `period`, allowing the system to deliver
+    /// each tick up to `leeway` late so it can coalesce wakeups and save power.
+    ///
+    /// The leeway is passed to the native timer on macOS and Windows, and ignored on
+    /// Linux.
+    pub fn with_leeway(period: Duration, leeway: Duration) -> Self {
+        let mut interval = Interval::new(period);
+        interval.inner.set_leeway(leeway);
+        interval
+    }
+
+    /// Like [`Interval::with_leeway`], with the leeway given as a fraction of the
+    /// period - `0.1` lets each tick be up to a tenth of a period late.
+    ///
+    /// The fraction is clamped to `0.0..=1.0`, and a NaN counts as zero.
+    pub fn with_relative_leeway(period: Duration, fraction: f64) -> Self {
+        Interval::with_leeway(period, relative_leeway(period, fraction))
+    }
+
+    /// Creates an interval whose ticks are each moved by a random amount within
+    /// `±jitter`, so that many clients started at the same time drift apart instead of
+    /// all ticking at once.
+    ///
+    /// The jitter is applied to each tick's place in the regular schedule, so on
+    /// average the interval still ticks every `period`. A jitter larger than the period
+    /// is clamped to it.
+    pub fn with_jitter(period: Duration, jitter: Duration) -> Self {
+        let mut interval = Interval::new(period);
+        interval.jitter = Some(Jitter::new(
+            jitter.min(period),
+            jitter_seed(&interval.inner),
+        ));
+        interval
+    }
+
+    /// Creates an interval whose first tick happens at `start`, and then every
+    /// `period` after that.
+    ///
+    /// If `start` has already passed by the time the interval is first polled, the
+    /// first tick is yielded by that poll, and the ticks after it follow a period
+    /// apart from then.
+    pub fn new_at(start: Instant, period: Duration) -> Self {
+        let mut interval = Interval::new(period);
+        interval.start = Some(start);
+        interval
+    }
+
+    /// Creates an interval whose first tick is yielded straight away by the first
+    /// poll, with the ticks after it following every `period`.
+    pub fn new_immediate(period: Duration) -> Self {
+        Interval::new_at(Instant::now(), period)
+    }
+
+    /// Creates an interval that yields a [`Tick`] for every tick, recording how many
+    /// periods it covers.
+    pub fn counting(period: Duration) -> CountingInterval {
+        CountingInterval {
+            inner: Interval::new(period),
+        }
+    }
+
+    /// Creates an interval that yields `()` for every tick, rather than the `Instant`
+    /// it was yielded at.
+    ///
+    /// Reading the clock for every tick costs a little, so for an interval used
+    /// purely as a clock tick - especially a fast one - this saves the call.
+    pub fn new_lightweight(period: Duration) -> LightweightInterval {
+        Interval::new(period).lightweight()
+    }
+
+    /// Turns this interval into one that yields `()` for every tick, like one from
+    /// [`Interval::new_lightweight`].
+    pub fn lightweight(self) -> LightweightInterval {
+        LightweightInterval { inner: self }
+    }
+
+    /// Turns this interval into a delay of one period, reusing its native timer rather
+    /// than creating a new one.
+    ///
+    /// The delay starts counting when it is first polled, like one from
+    /// [`Delay::new`], and any tick that was still pending is discarded.
+    pub fn once(mut self) -> Delay {
+        self.inner.disarm();
+        self.inner.state.clear();
+        Delay::from_timer(self.inner, self.interval, None)
+    }
+
+    /// Turns this interval into a stream that ends after `n` more ticks, stopping the
+    /// native timer as soon as the last one has been yielded.
+    pub fn take_ticks(self, n: usize) -> TakeTicks {
+        TakeTicks {
+            inner: self,
+            remaining: n,
+        }
+    }
+
+    /// Returns the period between ticks.
+    pub fn period(&self) -> Duration {
+        self.interval
+    }
+
+    /// Changes the period between ticks.
+    ///
+    /// If the interval is already running, the tick that is currently scheduled still
+    /// happens as planned, and the new period applies from then on.
+    pub fn set_period(&mut self, period: Duration) {
+        self.interval = period;
+        self.period_changed = self.inner.is_active();
+    }
+
+    /// Restarts the interval so that the next tick is a full period from now.
+    ///
+    /// Any tick that was already pending is discarded. This also restarts an interval
+    /// that was stopped.
+    pub fn reset(&mut self) {
+        // a tick of the old schedule can still be on its way after this, but clearing
+        // ends the old arming, and the timer's state drops anything that arrives for it.
+        self.inner.disarm();
+        self.inner.state.clear();
+
+        self.schedule(None);
+        self.period_changed = false;
+        self.terminated = false;
+    }
+
+    /// Disarms the native timer and ends the stream, without dropping the interval.
+    ///
+    /// A stopped interval yields `None` and `is_terminated` returns `true`, until it is
+    /// started again with [`Interval::restart`] or [`Interval::reset`]. A tick that was
+    /// already pending when the interval was stopped is discarded, as is one the os
+    /// timer still delivers afterwards, so neither shows up after a restart.
+    pub fn stop(&mut self) {
+        self.inner.disarm();
+        self.inner.state.clear();
+        self.period_changed = false;
+        self.terminated = true;
+    }
+
+    /// Starts a stopped interval again, with the next tick a full period from now.
+    ///
+    /// This does nothing if the interval is still running - see [`Interval::reset`] to
+    /// restart that one's schedule.
+    pub fn restart(&mut self) {
+        if self.terminated {
+            self.reset();
+        }
+    }
+
+    /// Returns whether the interval has been stopped.
+    pub fn is_stopped(&self) -> bool {
+        self.terminated
+    }
+
+    /// Returns whether the native timer is currently armed.
+    ///
+    /// An interval only arms its timer when it is first polled, so this is `false`
+    /// until then, and again once the interval has been stopped.
+    pub fn is_active(&self) -> bool {
+        self.inner.is_active()
+    }
+
+    /// Restarts the interval, yielding the next tick straight away. The ticks after
+    /// that follow a full period apart, starting from now.
+    pub fn reset_immediately(&mut self) {
+        self.reset();
+        self.inner.state.fire();
+    }
+
+    /// Returns how many periods had elapsed when the last tick was yielded.
+    ///
+    /// Anything above one means the stream wasn't polled in time and ticks were
+    /// coalesced (or, with [`MissedTickBehavior::Burst`], are still queued up).
+    pub fn missed_ticks(&self) -> usize {
+        self.missed_ticks
+    }
+
+    /// Returns how many ticks the interval has yielded, since it was created or since
+    /// the last [`Interval::reset_ticks`].
+    ///
+    /// Ticks that were coalesced into one (see [`Interval::missed_ticks`]) only count
+    /// once, and restarting the interval doesn't clear the count.
+    pub fn ticks(&self) -> u64 {
+        self.yielded
+    }
+
+    /// Sets the count returned by [`Interval::ticks`] back to zero.
+    pub fn reset_ticks(&mut self) {
+        self.yielded = 0;
+    }
+
+    /// Returns how this interval catches up on missed ticks.
+    pub fn missed_tick_behavior(&self) -> MissedTickBehavior {
+        self.missed_tick_behavior
+    }
+
+    /// Sets how this interval catches up on missed ticks.
+    pub fn set_missed_tick_behavior(&mut self, behavior: MissedTickBehavior) {
+        self.missed_tick_behavior = behavior;
+    }
+
+    /// Polls for the next tick, without reading the clock for it.
+    fn poll_tick(&mut self, lw: &Waker) -> Poll<Option<()>> {
+        if self.terminated {
+            return Poll::Ready(None);
+        }
+
+        // register before arming, so that a tick landing straight away still has a
+        // waker to wake.
+        self.inner.register_waker(lw);
+        if !self.inner.is_active() {
+            let start = self.start.take();
+            self.schedule(start);
+        }
+
+        let ticks = match self.missed_tick_behavior {
+            MissedTickBehavior::Burst => self.inner.state.take_tick(),
+            MissedTickBehavior::Delay | MissedTickBehavior::Skip => self.inner.state.take_ticks(),
+        };
+
+        if ticks == 0 {
+            return Poll::Pending;
+        }
+
+        self.missed_ticks = ticks;
+        self.yielded += 1;
+        let interval = self.interval;
+
+        if self.period_changed {
+            // the tick that was scheduled under the old period has now been yielded.
+            self.period_changed = false;
+            self.schedule(None);
+        } else if self.missed_tick_behavior == MissedTickBehavior::Delay && ticks > 1 {
+            // push the schedule back so the next tick is a full period from now -
+            // clearing the missed ticks first, so none of the new schedule's are lost.
+            self.inner.state.clear();
+            self.schedule(None);
+        } else if let Some(ref mut jitter) = self.jitter {
+            jitter.base = saturating_add(jitter.base, interval);
+            self.arm_jittered();
+        }
+
+        Poll::Ready(Some(()))
+    }
+
+    /// Arms the timer for a regular schedule, with the first tick at `first` if there
+    /// is one, and a period from now otherwise.
+    fn schedule(&mut self, first: Option<Instant>) {
+        let interval = self.interval;
+
+        if let Some(ref mut jitter) = self.jitter {
+            jitter.base = first.unwrap_or_else(|| saturating_add(Instant::now(), interval));
+            self.arm_jittered();
+            return;
+        }
+
+        match first {
+            // already due - tick straight away instead of waiting for the os timer to
+            // get round to it.
+            Some(first) if first <= Instant::now() => {
+                self.inner.init_interval(interval);
+                self.inner.state.fire();
+            }
+            Some(first) => self.inner.init_interval_at(until(first), interval),
+            None => self.inner.init_interval(interval),
+        }
+    }
 
-        Interval { inner, interval }
+    /// Arms the timer for the next tick of a jittered interval - these are one-shot, so
+    /// every tick gets its own random offset.
+    fn arm_jittered(&mut self) {
+        if let Some(ref mut jitter) = self.jitter {
+            let base = jitter.base;
+            let at = jitter.perturb(base);
+            self.inner.init_delay(until(at));
+        }
+    }
+
+    /// Returns the os timer behind this interval - a `timer_t` on Linux (or a `timerfd`
+    /// with the `timerfd` feature), a `dispatch_source_t` on macOS, a `PTP_TIMER` on
+    /// Windows, the kqueue `ident` on the BSDs, the `setInterval` id in the browser,
+    /// and the driving thread with the `fallback` backend.
+    ///
+    /// Returns `None` for timers driven by a `TimerWheel`, which don't have one.
+    ///
+    /// # Safety
+    ///
+    /// The handle is still owned by this interval, and is handed on to a new timer once it
+    /// is dropped. Anything done with it - especially re-arming, disarming or
+    /// deleting it - is the caller's responsibility, and can break the interval.
+    pub unsafe fn raw_handle(&self) -> Option<RawTimer> {
+        self.inner.handle.raw()
     }
 }
 
@@ -25,25 +431,752 @@ impl Stream for Interval {
     type Item = Instant;
 
     fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
-        if !self.inner.is_active() {
-            let interval = self.interval;
-            self.inner.handle.init_interval(interval);
+        self.poll_tick(lw).map(|tick| tick.map(|()| Instant::now()))
+    }
+}
+
+impl FusedStream for Interval {
+    /// An interval never runs out of ticks by itself, so this is only `true` once it
+    /// has been stopped or a [`TakeTicks`] has used up its ticks.
+    fn is_terminated(&self) -> bool {
+        self.terminated
+    }
+}
+
+impl Unpin for Interval {}
+
+/// Creates a new interval with the same configuration, which starts out unarmed.
+///
+/// A clone gets a native timer of its own - driven the same way, with the same leeway,
+/// missed tick behavior and jitter - and none of the original's timing state: its
+/// ticks start a period after it is first polled, or at the original's start time if
+/// the original hasn't been polled yet, and its tick count starts from zero. Cloning a
+/// stopped interval gives a running one.
+///
+/// # Panics
+///
+/// Panics if the native timer can't be created.
+impl Clone for Interval {
+    fn clone(&self) -> Self {
+        let inner = self
+            .inner
+            .try_clone()
+            .expect("error creating native timer!");
+        let mut interval = Interval::from_timer(inner, self.interval, self.start);
+        interval.missed_tick_behavior = self.missed_tick_behavior;
+
+        if let Some(ref jitter) = self.jitter {
+            interval.jitter = Some(Jitter::new(jitter.amount, jitter_seed(&interval.inner)));
         }
 
-        self.inner.register_waker(lw);
-        if self.inner.is_done() {
-            self.inner.state.set_done(false);
-            Poll::Ready(Some(Instant::now()))
-        } else {
-            Poll::Pending
+        interval
+    }
+}
+
+impl fmt::Debug for Interval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Interval")
+            .field("period", &self.interval)
+            .field("armed", &self.inner.is_active())
+            .finish()
+    }
+}
+
+/// Seeds the jitter of an interval driven by `timer`.
+fn jitter_seed(timer: &Timer) -> u64 {
+    // nothing fancy - the address of the shared state and the time are plenty to keep
+    // different intervals apart.
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_nanos() as u64)
+        .unwrap_or(0);
+
+    &*timer.state as *const _ as u64 ^ now
+}
+
+/// Returns `fraction` of `period`, with the fraction clamped to `0.0..=1.0`.
+fn relative_leeway(period: Duration, fraction: f64) -> Duration {
+    // written so that a NaN fails both tests, and ends up as zero.
+    let fraction = if fraction > 0.0 {
+        fraction.min(1.0)
+    } else {
+        0.0
+    };
+
+    let nanos = (period.as_nanos() as f64 * fraction) as u128;
+    Duration::new(
+        (nanos / 1_000_000_000) as u64,
+        (nanos % 1_000_000_000) as u32,
+    )
+}
+
+/// A tick yielded by a [`CountingInterval`].
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Tick {
+    /// When the tick was yielded.
+    pub at: Instant,
+    /// How many periods elapsed for this tick. Anything above one means ticks were
+    /// missed since the previous one.
+    pub elapsed_periods: u32,
+}
+
+/// An [`Interval`] that yields [`Tick`]s, created by [`Interval::counting`].
+#[derive(Debug)]
+pub struct CountingInterval {
+    inner: Interval,
+}
+
+impl CountingInterval {
+    /// Returns a reference to the underlying interval.
+    pub fn get_ref(&self) -> &Interval {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying interval.
+    pub fn get_mut(&mut self) -> &mut Interval {
+        &mut self.inner
+    }
+}
+
+impl Stream for CountingInterval {
+    type Item = Tick;
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        Pin::new(&mut self.inner).poll_next(lw).map(|tick| {
+            let periods = self.inner.missed_ticks();
+            tick.map(|at| Tick {
+                at,
+                elapsed_periods: periods as u32,
+            })
+        })
+    }
+}
+
+impl FusedStream for CountingInterval {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl Unpin for CountingInterval {}
+
+/// An [`Interval`] that yields `()` for every tick, created by
+/// [`Interval::new_lightweight`].
+#[derive(Debug)]
+pub struct LightweightInterval {
+    inner: Interval,
+}
+
+impl LightweightInterval {
+    /// Returns a reference to the underlying interval.
+    pub fn get_ref(&self) -> &Interval {
+        &self.inner
+    }
+
+    /// Returns a mutable reference to the underlying interval.
+    pub fn get_mut(&mut self) -> &mut Interval {
+        &mut self.inner
+    }
+
+    /// Returns the underlying interval, which yields `Instant`s again.
+    pub fn into_inner(self) -> Interval {
+        self.inner
+    }
+}
+
+impl Stream for LightweightInterval {
+    type Item = ();
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        self.inner.poll_tick(lw)
+    }
+}
+
+impl FusedStream for LightweightInterval {
+    fn is_terminated(&self) -> bool {
+        self.inner.is_terminated()
+    }
+}
+
+impl Unpin for LightweightInterval {}
+
+/// An [`Interval`] that ends after a fixed number of ticks, created by
+/// [`Interval::take_ticks`].
+#[derive(Debug)]
+pub struct TakeTicks {
+    inner: Interval,
+    remaining: usize,
+}
+
+impl TakeTicks {
+    /// Returns how many ticks are left before the stream ends.
+    pub fn remaining(&self) -> usize {
+        self.remaining
+    }
+
+    /// Returns a reference to the underlying interval.
+    pub fn get_ref(&self) -> &Interval {
+        &self.inner
+    }
+}
+
+impl Stream for TakeTicks {
+    type Item = Instant;
+
+    fn poll_next(mut self: Pin<&mut Self>, lw: &Waker) -> Poll<Option<Self::Item>> {
+        if self.remaining == 0 {
+            return Poll::Ready(None);
+        }
+
+        let tick = match Pin::new(&mut self.inner).poll_next(lw) {
+            Poll::Ready(tick) => tick,
+            Poll::Pending => return Poll::Pending,
+        };
+
+        self.remaining -= 1;
+        if self.remaining == 0 {
+            // nothing will poll for the next tick, so don't leave the os timer running
+            // until the stream is dropped.
+            self.inner.stop();
         }
+
+        Poll::Ready(tick)
     }
 }
 
-impl FusedStream for Interval {
+impl FusedStream for TakeTicks {
     fn is_terminated(&self) -> bool {
-        false
+        self.remaining == 0
     }
 }
 
-impl Unpin for Interval {}
+impl Unpin for TakeTicks {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{MockTimer, TimerSource};
+    use futures::executor::block_on;
+    use futures::task::noop_waker_ref;
+    use std::thread;
+
+    #[test]
+    fn stale_tick_after_restart() {
+        let mut interval = Interval::new(Duration::from_secs(3600));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        let generation = interval.inner.state.generation();
+        interval.stop();
+        interval.restart();
+
+        // the old schedule's expiry, delivered only now - both the way backends that
+        // can tell which arming it was for do it, and the way the rest do.
+        interval.inner.state.fire_for(generation, 1);
+        interval.inner.state.fire_expired(Duration::from_secs(0));
+
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+    }
+
+    #[test]
+    fn take_ticks() {
+        let mut ticks = Interval::new(Duration::from_millis(20)).take_ticks(3);
+
+        let work = async {
+            let mut count = 0;
+            while let Some(_) = await!(ticks.next()) {
+                count += 1;
+            }
+
+            count
+        };
+
+        assert_eq!(block_on(work), 3);
+        assert!(ticks.is_terminated());
+        assert!(ticks.get_ref().is_terminated());
+        assert!(!ticks.get_ref().is_active());
+
+        // stays finished.
+        assert_eq!(block_on(ticks.next()), None);
+    }
+
+    #[test]
+    fn count_ticks() {
+        let mut interval = Interval::new(Duration::from_millis(5));
+        assert_eq!(interval.ticks(), 0);
+
+        for _ in 0..3 {
+            block_on(interval.next());
+        }
+        assert_eq!(interval.ticks(), 3);
+
+        // the count carries on across a restart.
+        interval.stop();
+        interval.restart();
+        block_on(interval.next());
+        assert_eq!(interval.ticks(), 4);
+
+        interval.reset_ticks();
+        assert_eq!(interval.ticks(), 0);
+        block_on(interval.next());
+        assert_eq!(interval.ticks(), 1);
+    }
+
+    #[test]
+    fn clone_interval() {
+        let mut interval = Interval::new(Duration::from_millis(5));
+        interval.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        block_on(interval.next());
+        interval.stop();
+
+        let mut clone = interval.clone();
+        assert_eq!(clone.missed_tick_behavior(), MissedTickBehavior::Burst);
+        assert_eq!(clone.ticks(), 0);
+        assert!(!clone.is_stopped());
+        assert!(!clone.is_active());
+
+        assert!(block_on(clone.next()).is_some());
+        assert!(interval.is_stopped());
+    }
+
+    #[test]
+    fn lightweight_ticks() {
+        let mut interval = Interval::new_lightweight(Duration::from_millis(5));
+
+        for _ in 0..3 {
+            assert_eq!(block_on(interval.next()), Some(()));
+        }
+        assert_eq!(interval.get_ref().ticks(), 3);
+
+        // back to timestamps, on the same schedule.
+        let mut interval = interval.into_inner();
+        assert!(block_on(interval.next()).is_some());
+        assert_eq!(interval.ticks(), 4);
+    }
+
+    #[test]
+    fn relative_leeway_clamps() {
+        let period = Duration::from_millis(200);
+
+        assert_eq!(relative_leeway(period, 0.1), Duration::from_millis(20));
+        assert_eq!(relative_leeway(period, 1.5), period);
+        assert_eq!(relative_leeway(period, -1.0), Duration::from_secs(0));
+        assert_eq!(
+            relative_leeway(period, std::f64::NAN),
+            Duration::from_secs(0)
+        );
+
+        let stream = Interval::with_relative_leeway(Duration::from_millis(5), 0.2);
+        assert_eq!(block_on(stream.take(3).collect::<Vec<_>>()).len(), 3);
+    }
+
+    #[test]
+    fn armed_on_first_poll() {
+        let mut interval = Interval::new(Duration::from_millis(20));
+        assert!(!interval.is_active());
+
+        block_on(interval.next());
+        assert!(interval.is_active());
+
+        interval.stop();
+        assert!(!interval.is_active());
+
+        interval.restart();
+        assert!(interval.is_active());
+    }
+
+    #[test]
+    fn immediate_first_tick() {
+        let period = Duration::from_millis(200);
+        let mut interval = Interval::new_immediate(period);
+
+        let start = Instant::now();
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_ready());
+        assert!(start.elapsed() < period / 4);
+
+        // the rest follow the period.
+        block_on(interval.next());
+        assert!(start.elapsed() >= period);
+    }
+
+    #[test]
+    fn jitter_stays_in_range() {
+        let amount = Duration::from_millis(5);
+        let mut jitter = Jitter::new(amount, 0x2545_f491_4f6c_dd1d);
+        let base = Instant::now() + Duration::from_secs(1);
+
+        let mut total: i128 = 0;
+        for _ in 0..10_000 {
+            let at = jitter.perturb(base);
+            assert!(at >= base - amount && at <= base + amount);
+
+            total += if at >= base {
+                (at - base).as_nanos() as i128
+            } else {
+                -((base - at).as_nanos() as i128)
+            };
+        }
+
+        // the offsets average out to (nearly) nothing.
+        let mean = total / 10_000;
+        assert!(mean.abs() < Duration::from_micros(250).as_nanos() as i128);
+    }
+
+    #[test]
+    fn intervals() {
+        let mock = MockTimer::new();
+        let mut timeout = mock.delay(Duration::from_secs(1));
+        let mut stream = mock.interval(Duration::from_millis(99));
+
+        assert!(Pin::new(&mut timeout).poll(noop_waker_ref()).is_pending());
+        assert!(Pin::new(&mut stream)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        let mut total = 0;
+        loop {
+            mock.advance(Duration::from_millis(1));
+
+            if Pin::new(&mut stream).poll_next(noop_waker_ref()).is_ready() {
+                total += 1;
+            }
+            if Pin::new(&mut timeout).poll(noop_waker_ref()).is_ready() {
+                break;
+            }
+        }
+
+        assert_eq!(total, 10);
+    }
+
+    #[test]
+    fn jittered_interval() {
+        let period = Duration::from_millis(10);
+        let stream = Interval::with_jitter(period, Duration::from_millis(5));
+
+        let start = Instant::now();
+        let ticks = block_on(stream.take(50).collect::<Vec<_>>());
+        let elapsed = start.elapsed();
+
+        // the ticks keep to the schedule on average...
+        assert!(elapsed >= Duration::from_millis(450));
+        assert!(elapsed < Duration::from_millis(800));
+
+        // ...but aren't evenly spaced.
+        let gaps: Vec<_> = ticks.windows(2).map(|w| w[1] - w[0]).collect();
+        let uneven = gaps
+            .iter()
+            .filter(|&&gap| gap < Duration::from_millis(8) || gap > Duration::from_millis(12))
+            .count();
+        assert!(uneven > 5);
+    }
+
+    #[test]
+    fn missed_ticks() {
+        fn drain(interval: &mut Interval) -> usize {
+            let mut ready = 0;
+            while Pin::new(&mut *interval)
+                .poll_next(noop_waker_ref())
+                .is_ready()
+            {
+                ready += 1;
+            }
+
+            ready
+        }
+
+        let mut burst = Interval::new(Duration::from_millis(20));
+        burst.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        let mut skip = Interval::new(Duration::from_millis(20));
+        skip.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        assert_eq!(drain(&mut burst), 0);
+        assert_eq!(drain(&mut skip), 0);
+
+        thread::sleep(Duration::from_millis(110));
+        assert!(drain(&mut burst) >= 4);
+        assert_eq!(drain(&mut skip), 1);
+    }
+
+    #[test]
+    fn missed_ticks_on_mock() {
+        fn drain(interval: &mut Interval) -> usize {
+            let mut ready = 0;
+            while Pin::new(&mut *interval)
+                .poll_next(noop_waker_ref())
+                .is_ready()
+            {
+                ready += 1;
+            }
+
+            ready
+        }
+
+        let mock = MockTimer::new();
+        let mut burst = mock.interval(Duration::from_millis(20));
+        burst.set_missed_tick_behavior(MissedTickBehavior::Burst);
+        let mut skip = mock.interval(Duration::from_millis(20));
+        skip.set_missed_tick_behavior(MissedTickBehavior::Skip);
+
+        assert_eq!(drain(&mut burst), 0);
+        assert_eq!(drain(&mut skip), 0);
+
+        mock.advance(Duration::from_millis(110));
+        assert_eq!(drain(&mut burst), 5);
+        assert_eq!(drain(&mut skip), 1);
+    }
+
+    #[test]
+    fn count_missed_ticks() {
+        let mut interval = Interval::new(Duration::from_millis(20));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        thread::sleep(Duration::from_millis(100));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_ready());
+        assert!(interval.missed_ticks() >= 4);
+    }
+
+    #[test]
+    fn count_missed_ticks_on_mock() {
+        let mock = MockTimer::new();
+        let mut interval = mock.interval(Duration::from_millis(20));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        mock.advance(Duration::from_millis(100));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_ready());
+        assert_eq!(interval.missed_ticks(), 5);
+    }
+
+    #[test]
+    fn reset_interval() {
+        let mut interval = Interval::new(Duration::from_millis(100));
+
+        let work = async {
+            await!(interval.next());
+            thread::sleep(Duration::from_millis(60));
+
+            let t = Instant::now();
+            interval.reset();
+            await!(interval.next());
+            let full = t.elapsed();
+
+            let t = Instant::now();
+            interval.reset_immediately();
+            await!(interval.next());
+            (full, t.elapsed())
+        };
+
+        let (full, immediate) = block_on(work);
+        assert!(full >= Duration::from_millis(90));
+        assert!(immediate < Duration::from_millis(50));
+    }
+
+    #[test]
+    fn reset_interval_on_mock() {
+        let mock = MockTimer::new();
+        let mut interval = mock.interval(Duration::from_millis(100));
+        let waker = noop_waker_ref();
+
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(100));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+        mock.advance(Duration::from_millis(60));
+
+        // the next tick is a full period after the reset, not the one already due.
+        interval.reset();
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(99));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(1));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+
+        interval.reset_immediately();
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+    }
+
+    #[test]
+    fn stop_interval() {
+        let mut interval = Interval::new(Duration::from_millis(40));
+        assert!(!interval.is_terminated());
+
+        let work = async {
+            await!(interval.next());
+
+            // let the next tick fire without being yielded, then stop the interval.
+            thread::sleep(Duration::from_millis(50));
+            interval.stop();
+
+            let stopped = (await!(interval.next()), interval.is_terminated());
+
+            // the tick that was pending when the interval stopped is gone.
+            let t = Instant::now();
+            interval.restart();
+            await!(interval.next());
+
+            (stopped, t.elapsed())
+        };
+
+        let ((after_stop, terminated), restarted) = block_on(work);
+        assert_eq!(after_stop, None);
+        assert!(terminated);
+        assert!(restarted >= Duration::from_millis(30));
+        assert!(!interval.is_terminated());
+        assert!(!interval.is_stopped());
+    }
+
+    #[test]
+    fn stop_interval_on_mock() {
+        let mock = MockTimer::new();
+        let mut interval = mock.interval(Duration::from_millis(40));
+        let waker = noop_waker_ref();
+        assert!(!interval.is_terminated());
+
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(40));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+
+        // let the next tick fire without being yielded, then stop the interval.
+        mock.advance(Duration::from_millis(50));
+        interval.stop();
+        assert_eq!(Pin::new(&mut interval).poll_next(waker), Poll::Ready(None));
+        assert!(interval.is_terminated());
+
+        // the tick that was pending when the interval stopped is gone.
+        interval.restart();
+        assert!(!interval.is_terminated());
+        assert!(!interval.is_stopped());
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(39));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(1));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+    }
+
+    #[test]
+    fn change_interval_period() {
+        let mut interval = Interval::new(Duration::from_millis(100));
+
+        let work = async {
+            let t = Instant::now();
+            await!(interval.next());
+            await!(interval.next());
+            let slow = t.elapsed();
+
+            interval.set_period(Duration::from_millis(30));
+            assert_eq!(interval.period(), Duration::from_millis(30));
+
+            // the tick already scheduled at the old period
+            await!(interval.next());
+
+            let t = Instant::now();
+            await!(interval.next());
+            await!(interval.next());
+            (slow, t.elapsed())
+        };
+
+        let (slow, fast) = block_on(work);
+        assert!(slow >= Duration::from_millis(190));
+        assert!(fast < Duration::from_millis(100));
+    }
+
+    #[test]
+    fn change_interval_period_on_mock() {
+        let mock = MockTimer::new();
+        let mut interval = mock.interval(Duration::from_millis(100));
+        let waker = noop_waker_ref();
+
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(100));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+
+        interval.set_period(Duration::from_millis(30));
+        assert_eq!(interval.period(), Duration::from_millis(30));
+
+        // the tick already scheduled at the old period
+        mock.advance(Duration::from_millis(99));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(1));
+        assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+
+        for _ in 0..2 {
+            mock.advance(Duration::from_millis(29));
+            assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+            mock.advance(Duration::from_millis(1));
+            assert!(Pin::new(&mut interval).poll_next(waker).is_ready());
+        }
+    }
+
+    #[test]
+    fn interval_at_start() {
+        let start = Instant::now() + Duration::from_millis(50);
+        let mut interval = Interval::new_at(start, Duration::from_millis(100));
+
+        let work = async {
+            await!(interval.next());
+            let first = start.elapsed();
+            await!(interval.next());
+            (first, start.elapsed())
+        };
+
+        let (first, second) = block_on(work);
+        assert!(first < Duration::from_millis(50));
+        assert!(second >= Duration::from_millis(90));
+        assert!(second < Duration::from_millis(150));
+    }
+
+    #[test]
+    fn counting_interval() {
+        let mut interval = Interval::counting(Duration::from_millis(20));
+
+        let work = async {
+            let first = await!(interval.next()).unwrap();
+            thread::sleep(Duration::from_millis(100));
+            let second = await!(interval.next()).unwrap();
+            (first, second)
+        };
+
+        let (first, second) = block_on(work);
+        assert_eq!(first.elapsed_periods, 1);
+        assert!(second.elapsed_periods >= 4);
+        assert!(second.at > first.at);
+    }
+
+    #[test]
+    fn counting_interval_on_mock() {
+        let mock = MockTimer::new();
+        let mut interval = CountingInterval {
+            inner: mock.interval(Duration::from_millis(20)),
+        };
+        let waker = noop_waker_ref();
+
+        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+        mock.advance(Duration::from_millis(20));
+        let first = match Pin::new(&mut interval).poll_next(waker) {
+            Poll::Ready(Some(tick)) => tick,
+            res => panic!("expected a tick, got {:?}", res),
+        };
+
+        mock.advance(Duration::from_millis(100));
+        let second = match Pin::new(&mut interval).poll_next(waker) {
+            Poll::Ready(Some(tick)) => tick,
+            res => panic!("expected a tick, got {:?}", res),
+        };
+
+        assert_eq!(first.elapsed_periods, 1);
+        assert_eq!(second.elapsed_periods, 5);
+        assert!(second.at >= first.at);
+    }
+}
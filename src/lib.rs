@@ -1,10 +1,15 @@
 #![feature(futures_api, async_await, await_macro)]
 
-use std::sync::atomic::AtomicBool;
-use std::sync::atomic::Ordering::SeqCst;
-use std::sync::Arc;
+use std::io;
+use std::mem::ManuallyDrop;
+use std::ptr;
+use std::sync::atomic::AtomicUsize;
+use std::sync::atomic::Ordering::{AcqRel, Acquire, Relaxed, Release};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, ThreadId};
+use std::time::{Duration, Instant, SystemTime};
 
-use futures::task::{AtomicWaker, Waker};
+use futures::task::{noop_waker_ref, AtomicWaker, Waker};
 
 #[macro_export]
 macro_rules! dbg_println {
@@ -16,76 +21,617 @@ macro_rules! dbg_println {
     };
 }
 
+mod backoff;
+mod builder;
+mod clock;
+pub mod compat;
+mod deadline;
+mod debounce;
 mod delay;
+mod error;
+mod idle;
 mod interval;
+mod mock;
+mod observer;
+mod pool;
+mod qos;
+mod reactor;
+mod source;
+#[cfg(feature = "testing")]
+pub mod testing;
+mod throttle;
+mod time_base;
 mod timeout;
+mod wake;
+#[cfg(feature = "wheel")]
+mod wheel;
 
-#[cfg(windows)]
+#[cfg(all(windows, not(feature = "fallback")))]
 #[path = "sys/windows.rs"]
 mod imp;
 
-#[cfg(target_os = "linux")]
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "timerfd"),
+    not(feature = "fallback")
+))]
 #[path = "sys/linux.rs"]
 mod imp;
 
-#[cfg(target_os = "macos")]
+#[cfg(all(target_os = "linux", feature = "timerfd", not(feature = "fallback")))]
+#[path = "sys/timerfd.rs"]
+mod imp;
+
+#[cfg(all(target_os = "macos", not(feature = "fallback")))]
 #[path = "sys/macos.rs"]
 mod imp;
 
+#[cfg(all(
+    any(
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly"
+    ),
+    not(feature = "fallback")
+))]
+#[path = "sys/bsd.rs"]
+mod imp;
+
+#[cfg(all(target_arch = "wasm32", not(feature = "fallback")))]
+#[path = "sys/wasm.rs"]
+mod imp;
+
+// everywhere else, or when asked for, a thread per timer.
+#[cfg(any(
+    feature = "fallback",
+    not(any(
+        windows,
+        target_os = "linux",
+        target_os = "macos",
+        target_os = "freebsd",
+        target_os = "openbsd",
+        target_os = "netbsd",
+        target_os = "dragonfly",
+        target_arch = "wasm32"
+    ))
+))]
+#[path = "sys/fallback.rs"]
+mod imp;
+
 use imp::NativeTimer;
+pub use imp::RawTimer;
+use mock::MockHandle;
+use pool::TimerPool;
+#[cfg(feature = "wheel")]
+use wheel::WheelTimer;
 
-pub use delay::Delay;
-pub use interval::Interval;
-pub use timeout::{FutureExt, Timeout, TimeoutError};
+pub use backoff::Backoff;
+pub use builder::TimerBuilder;
+pub use clock::Clock;
+pub use deadline::Deadline;
+pub use debounce::Debounce;
+pub use delay::{join_ordered, sleep, sleep_until, Delay, DelayWith, JoinOrdered};
+pub use error::{Error, TimerError};
+pub use idle::{IdleBarrier, IdleHandle};
+pub use interval::{
+    CountingInterval, Interval, LightweightInterval, MissedTickBehavior, TakeTicks, Tick,
+};
+pub use mock::MockTimer;
+pub use observer::{set_timer_observer, TimerEvent};
+pub use qos::QosClass;
+pub use reactor::Reactor;
+pub use source::{NativeTimers, TimerSource};
+pub use throttle::Throttle;
+pub use time_base::TimeBase;
+pub use timeout::{
+    AtLeast, FutureExt, OrTimeout, StreamExt, Timeout, TimeoutError, TimeoutMap, TimeoutStream,
+    TimeoutWithRemaining,
+};
+pub use wake::{drive_ready_timers, WakeStrategy};
+#[cfg(feature = "wheel")]
+pub use wheel::TimerWheel;
 
 #[derive(Debug)]
 pub(crate) struct TimerState {
     wake: AtomicWaker,
-    done: AtomicBool,
+    ticks: AtomicUsize,
+    // how many times `wake` has handed its waker out - pollers compare this to decide
+    // whether their waker is still registered.
+    wakes: AtomicUsize,
+    // the duration the timer was last armed with, split in two to fit in `usize`s -
+    // only read to report events.
+    armed_secs: AtomicUsize,
+    armed_nanos: AtomicUsize,
+    // which arming of the timer is current. expiries can still be on their way from an
+    // earlier one after the timer has been disarmed or re-armed, and the callbacks check
+    // them against this before counting them.
+    arming: Mutex<Arming>,
+}
+
+#[derive(Debug)]
+struct Arming {
+    // bumped every time the timer is armed, disarmed or cleared.
+    generation: usize,
+    due: Due,
+}
+
+/// When the current arming of a timer can first expire - for backends whose callbacks
+/// can't say which arming an expiry belongs to.
+#[derive(Copy, Clone, Debug)]
+enum Due {
+    // disarmed, or due too far out to ever come.
+    Off,
+    // by either clock, as a timer on the realtime clock may follow the system time.
+    // intervals move on a period with every expiry.
+    At {
+        next: Instant,
+        wall: SystemTime,
+        period: Option<Duration>,
+    },
+}
+
+impl Due {
+    /// When a timer armed now to first expire after `start` is due.
+    fn after(start: Duration, period: Option<Duration>) -> Due {
+        let next = Instant::now().checked_add(start);
+        let wall = SystemTime::now().checked_add(start);
+
+        match (next, wall) {
+            (Some(next), Some(wall)) => Due::At { next, wall, period },
+            _ => Due::Off,
+        }
+    }
+
+    /// Whether the timer can have expired by now, allowing for one that fires up to
+    /// `early` ahead of time - moving an interval on to its next expiry if so.
+    fn expire(&mut self, early: Duration) -> bool {
+        let (next, wall, period) = match *self {
+            Due::Off => return false,
+            Due::At { next, wall, period } => (next, wall, period),
+        };
+
+        let now = Instant::now();
+        if now.checked_add(early).map_or(true, |now| now < next) {
+            let now = SystemTime::now();
+            if now.checked_add(early).map_or(true, |now| now < wall) {
+                return false;
+            }
+        }
+
+        *self = match period {
+            Some(period) => match (next.checked_add(period), wall.checked_add(period)) {
+                (Some(next), Some(wall)) => Due::At {
+                    next,
+                    wall,
+                    period: Some(period),
+                },
+                _ => Due::Off,
+            },
+            None => Due::Off,
+        };
+
+        true
+    }
 }
 
 impl TimerState {
     fn new() -> Self {
         TimerState {
             wake: AtomicWaker::new(),
-            done: false.into(),
+            ticks: AtomicUsize::new(0),
+            wakes: AtomicUsize::new(0),
+            armed_secs: AtomicUsize::new(0),
+            armed_nanos: AtomicUsize::new(0),
+            arming: Mutex::new(Arming {
+                generation: 0,
+                due: Due::Off,
+            }),
         }
     }
 
+    /// The pointer native callbacks are handed, which stays valid for as long as the
+    /// `Arc` holding this state is alive.
+    #[allow(dead_code)]
+    fn as_ptr(&self) -> *mut TimerState {
+        self as *const TimerState as *mut TimerState
+    }
+
     fn register_waker(&self, lw: &Waker) {
         self.wake.register(lw);
     }
 
-    fn set_done(&self, done: bool) {
-        self.done.store(done, SeqCst);
+    /// Adds an expiry straight away - for ticks the owner makes up itself, and for
+    /// backends that never deliver one late.
+    #[allow(dead_code)]
+    fn fire(&self) {
+        self.ticks.fetch_add(1, Release);
+        self.woken();
+    }
+
+    /// Called from the native timer callbacks with the `count` expirations of the
+    /// arming `generation` - which are dropped if the timer has been re-armed,
+    /// disarmed or cleared since.
+    #[allow(dead_code)]
+    fn fire_for(&self, generation: usize, count: usize) {
+        {
+            let arming = self.arming.lock().unwrap();
+            if arming.generation != generation {
+                return;
+            }
+
+            // the callback only publishes the ticks; whoever sees them with an `Acquire`
+            // load doesn't need anything stronger.
+            self.ticks.fetch_add(count, Release);
+        }
+
+        self.woken();
+    }
+
+    /// Like `fire_for`, for callbacks that can't say which arming an expiry belongs
+    /// to. The expiry only counts if the current arming can have expired by now, with
+    /// `early` allowing for timers that fire a little ahead of time. Returns whether it
+    /// did.
+    #[allow(dead_code)]
+    fn fire_expired(&self, early: Duration) -> bool {
+        {
+            let mut arming = self.arming.lock().unwrap();
+            if !arming.due.expire(early) {
+                return false;
+            }
+
+            self.ticks.fetch_add(1, Release);
+        }
+
+        self.woken();
+        true
+    }
+
+    /// Wakes whoever is waiting on new expirations - outside the arming lock, so that
+    /// the woken task can re-arm the timer from inside the wakeup.
+    fn woken(&self) {
+        self.wakes.fetch_add(1, Release);
+        observer::notify(TimerEvent::Fired(self.armed()));
+        self.wake.wake();
+    }
+
+    /// Starts a new arming of the timer, which first expires at `due`.
+    fn arm(&self, due: Due) {
+        let mut arming = self.arming.lock().unwrap();
+        arming.generation = arming.generation.wrapping_add(1);
+        arming.due = due;
+    }
+
+    /// Ends the current arming, so that none of its expiries count any more.
+    fn disarm(&self) {
+        self.arm(Due::Off);
+    }
+
+    /// The arming the timer is on - for backends to hand back to `fire_for`.
+    #[allow(dead_code)]
+    fn generation(&self) -> usize {
+        self.arming.lock().unwrap().generation
+    }
+
+    fn set_armed(&self, duration: Duration) {
+        self.armed_secs.store(duration.as_secs() as usize, Relaxed);
+        self.armed_nanos
+            .store(duration.subsec_nanos() as usize, Relaxed);
+    }
+
+    fn armed(&self) -> Duration {
+        let secs = self.armed_secs.load(Relaxed) as u64;
+        Duration::new(secs, self.armed_nanos.load(Relaxed) as u32)
+    }
+
+    fn wakes(&self) -> usize {
+        self.wakes.load(Acquire)
+    }
+
+    /// Throws away the pending expirations, and ends the current arming so that none
+    /// still on their way can show up afterwards.
+    fn clear(&self) {
+        let mut arming = self.arming.lock().unwrap();
+        arming.generation = arming.generation.wrapping_add(1);
+        arming.due = Due::Off;
+        self.ticks.store(0, Release);
+    }
+
+    /// Resets everything about the state, for a timer that goes back to the pool - the
+    /// next owner starts from scratch, and nothing still on its way for the old one
+    /// counts.
+    fn recycle(&self) {
+        self.clear();
+
+        // there's no taking the waker out, but a no-op one lets go of the old task.
+        self.wake.register(noop_waker_ref());
+        self.wakes.store(0, Release);
+        self.set_armed(Duration::from_secs(0));
     }
 
     fn done(&self) -> bool {
-        self.done.load(SeqCst)
+        self.ticks.load(Acquire) > 0
+    }
+
+    /// Takes all the expirations seen since the last call.
+    fn take_ticks(&self) -> usize {
+        self.ticks.swap(0, AcqRel)
+    }
+
+    /// Takes a single expiration, leaving any others for later.
+    ///
+    /// Returns how many expirations were pending beforehand.
+    fn take_tick(&self) -> usize {
+        let mut ticks = self.ticks.load(Acquire);
+        while ticks > 0 {
+            match self
+                .ticks
+                .compare_exchange(ticks, ticks - 1, AcqRel, Acquire)
+            {
+                Ok(_) => return ticks,
+                Err(actual) => ticks = actual,
+            }
+        }
+
+        0
+    }
+}
+
+/// What actually drives a `Timer` - normally an os timer of its own.
+#[derive(Debug)]
+enum Handle {
+    Native(NativeTimer),
+    #[cfg(feature = "wheel")]
+    Wheel(WheelTimer),
+    Mock(MockHandle),
+}
+
+macro_rules! dispatch {
+    ($handle:expr, $timer:ident => $e:expr) => {
+        match $handle {
+            Handle::Native($timer) => $e,
+            #[cfg(feature = "wheel")]
+            Handle::Wheel($timer) => $e,
+            Handle::Mock($timer) => $e,
+        }
+    };
+}
+
+impl Handle {
+    fn is_active(&self) -> bool {
+        dispatch!(self, timer => timer.is_active())
+    }
+
+    fn set_leeway(&mut self, leeway: Duration) {
+        dispatch!(self, timer => timer.set_leeway(leeway))
+    }
+
+    fn set_qos_class(&mut self, qos: QosClass) {
+        // only an os timer has callbacks for the system to schedule.
+        if let Handle::Native(timer) = self {
+            timer.set_qos_class(qos);
+        }
+    }
+
+    fn init_delay(&mut self, delay: Duration) {
+        dispatch!(self, timer => timer.init_delay(delay))
+    }
+
+    fn init_interval(&mut self, interval: Duration) {
+        dispatch!(self, timer => timer.init_interval(interval))
+    }
+
+    fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        dispatch!(self, timer => timer.init_interval_at(start, interval))
+    }
+
+    fn disarm(&mut self) {
+        dispatch!(self, timer => timer.disarm())
+    }
+
+    fn raw(&self) -> Option<RawTimer> {
+        match self {
+            Handle::Native(timer) => Some(timer.raw()),
+            #[cfg(feature = "wheel")]
+            Handle::Wheel(_) => None,
+            Handle::Mock(_) => None,
+        }
     }
 }
 
 #[derive(Debug)]
 struct Timer {
-    handle: NativeTimer,
+    // taken out in `drop`, so that the handle can go back to the pool.
+    handle: ManuallyDrop<Handle>,
     state: Arc<TimerState>,
+    clock: Clock,
+    owner: ThreadId,
+    // the waker last registered with `state`, and the `wakes` count from just before
+    // it was - while neither changes, there's no need to register it again.
+    waker: Option<(Waker, usize)>,
+    wake_strategy: WakeStrategy,
+    // what the handle was configured with, for `try_clone`.
+    leeway: Duration,
+    qos: QosClass,
+    // with a deferred or manual `WakeStrategy`, what actually gets registered in place
+    // of `waker`.
+    deferred: Option<Waker>,
 }
 
 impl Timer {
+    /// Creates a new timer, panicking if the native timer can't be created.
     pub fn new() -> Self {
+        Timer::try_new(Clock::default()).expect("error creating native timer!")
+    }
+
+    /// Creates a new timer measured against `clock`, returning the os error if the
+    /// native timer can't be created (e.g. because the process hit its timer limit).
+    pub fn try_new(clock: Clock) -> io::Result<Self> {
+        match TimerPool::take(clock) {
+            Some((handle, state)) => Ok(Timer::reused(handle, state, clock)),
+            None => Timer::create(clock),
+        }
+    }
+
+    /// Creates `n` new timers measured against `clock`, taking every idle timer it can
+    /// from the pool at once and creating only the rest.
+    fn try_new_batch(clock: Clock, n: usize) -> io::Result<Vec<Self>> {
+        let mut timers: Vec<_> = TimerPool::take_many(clock, n)
+            .into_iter()
+            .map(|(handle, state)| Timer::reused(handle, state, clock))
+            .collect();
+
+        while timers.len() < n {
+            timers.push(Timer::create(clock)?);
+        }
+
+        Ok(timers)
+    }
+
+    /// Wraps an idle timer taken from the pool.
+    fn reused(mut handle: NativeTimer, state: Arc<TimerState>, clock: Clock) -> Self {
+        // the old owner may have configured these - the state was already reset when
+        // the timer went back to the pool.
+        handle.set_leeway(Duration::from_secs(0));
+        handle.set_qos_class(QosClass::default());
+
+        Timer::from_parts(Handle::Native(handle), state, clock)
+    }
+
+    /// Creates a timer with a new os timer of its own.
+    fn create(clock: Clock) -> io::Result<Self> {
+        let state = Arc::new(TimerState::new());
+
+        // the native timer hands `state` to its callbacks as a raw pointer. backends whose
+        // callbacks can still run after the handle is dropped keep a reference of their
+        // own, and for the rest the timer (or the pool) keeps the `Arc` next to the handle.
+        let handle = match unsafe { NativeTimer::new(&state, clock) } {
+            Ok(handle) => handle,
+            Err(err) => {
+                // out of os timers - move on to the overflow wheel, if there is one.
+                #[cfg(feature = "wheel")]
+                {
+                    let limit = TimerError::of(&err) == Some(TimerError::LimitReached);
+                    if limit && clock == Clock::Monotonic {
+                        if let Some(wheel) = wheel::overflow() {
+                            return Ok(Timer::on_wheel(&wheel));
+                        }
+                    }
+                }
+
+                return Err(err);
+            }
+        };
+
+        Ok(Timer::from_parts(Handle::Native(handle), state, clock))
+    }
+
+    /// Creates a timer driven by `wheel` instead of an os timer of its own.
+    #[cfg(feature = "wheel")]
+    fn on_wheel(wheel: &TimerWheel) -> Self {
         let state = Arc::new(TimerState::new());
+        let handle = WheelTimer::new(wheel, state.clone());
 
-        unsafe {
-            let ptr = Arc::into_raw(state);
-            let handle = NativeTimer::new(ptr as *mut _);
-            let state = Arc::from_raw(ptr);
+        Timer::from_parts(Handle::Wheel(handle), state, Clock::Monotonic)
+    }
+
+    /// Creates a timer driven by a `MockTimer`'s clock.
+    fn mocked(mock: &MockTimer) -> Self {
+        let state = Arc::new(TimerState::new());
+        let handle = MockHandle::new(mock.clock().clone(), state.clone());
+
+        Timer::from_parts(Handle::Mock(handle), state, Clock::Monotonic)
+    }
+
+    fn from_parts(handle: Handle, state: Arc<TimerState>, clock: Clock) -> Self {
+        if let Handle::Native(_) = handle {
+            ACTIVE_TIMERS.fetch_add(1, Relaxed);
+        }
 
-            Timer { handle, state }
+        Timer {
+            handle: ManuallyDrop::new(handle),
+            state,
+            clock,
+            owner: thread::current().id(),
+            waker: None,
+            wake_strategy: WakeStrategy::default(),
+            leeway: Duration::from_secs(0),
+            qos: QosClass::default(),
+            deferred: None,
         }
     }
 
-    fn register_waker(&self, lw: &Waker) {
-        self.state.register_waker(lw);
+    /// Creates a new, unarmed timer driven the same way as this one - by an os timer,
+    /// the same wheel or the same mock clock - with the same settings.
+    fn try_clone(&self) -> io::Result<Self> {
+        let mut timer = match &*self.handle {
+            Handle::Native(_) => Timer::try_new(self.clock)?,
+            #[cfg(feature = "wheel")]
+            Handle::Wheel(handle) => {
+                let state = Arc::new(TimerState::new());
+                let handle = handle.sibling(state.clone());
+                Timer::from_parts(Handle::Wheel(handle), state, self.clock)
+            }
+            Handle::Mock(handle) => {
+                let state = Arc::new(TimerState::new());
+                let handle = handle.sibling(state.clone());
+                Timer::from_parts(Handle::Mock(handle), state, self.clock)
+            }
+        };
+
+        timer.set_leeway(self.leeway);
+        timer.set_qos_class(self.qos);
+        timer.set_wake_strategy(self.wake_strategy);
+        Ok(timer)
+    }
+
+    fn register_waker(&mut self, lw: &Waker) {
+        let wakes = self.state.wakes();
+
+        // waking takes the waker out of the `AtomicWaker`, so an unchanged waker still
+        // has to be registered again after every wakeup.
+        match &mut self.waker {
+            Some((waker, seen)) if waker.will_wake(lw) => {
+                if *seen == wakes {
+                    return;
+                }
+
+                *seen = wakes;
+            }
+            cached => {
+                *cached = Some((lw.clone(), wakes));
+                self.deferred = None;
+            }
+        }
+
+        match self.wake_strategy {
+            WakeStrategy::Inline => self.state.register_waker(lw),
+            strategy => {
+                let manual = strategy == WakeStrategy::Manual;
+                let deferred = self
+                    .deferred
+                    .get_or_insert_with(|| wake::deferred(lw, manual));
+                self.state.register_waker(deferred);
+            }
+        }
+    }
+
+    fn set_leeway(&mut self, leeway: Duration) {
+        self.leeway = leeway;
+        self.handle.set_leeway(leeway);
+    }
+
+    fn set_qos_class(&mut self, qos: QosClass) {
+        self.qos = qos;
+        self.handle.set_qos_class(qos);
+    }
+
+    fn set_wake_strategy(&mut self, strategy: WakeStrategy) {
+        self.wake_strategy = strategy;
+
+        // the next poll has to register its waker again, the new way.
+        self.waker = None;
+        self.deferred = None;
     }
 
     fn is_active(&self) -> bool {
@@ -95,6 +641,123 @@ impl Timer {
     fn is_done(&self) -> bool {
         self.state.done()
     }
+
+    fn init_delay(&mut self, delay: Duration) {
+        self.armed(delay, Due::after(delay, None));
+        self.handle.init_delay(delay);
+    }
+
+    fn init_interval(&mut self, interval: Duration) {
+        self.armed(interval, Due::after(interval, Some(interval)));
+        self.handle.init_interval(interval);
+    }
+
+    fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.armed(interval, Due::after(start, Some(interval)));
+        self.handle.init_interval_at(start, interval);
+    }
+
+    fn disarm(&mut self) {
+        self.cancelled();
+        self.state.disarm();
+        self.handle.disarm();
+    }
+
+    /// Records and reports what the timer is about to be armed with, and starts its
+    /// new arming - before arming, so that the timer can't fire first.
+    fn armed(&self, duration: Duration, due: Due) {
+        self.state.set_armed(duration);
+        self.state.arm(due);
+        observer::notify(TimerEvent::Armed(duration));
+    }
+
+    /// Reports the timer as cancelled, if it is armed and hasn't fired yet.
+    fn cancelled(&self) {
+        if self.is_active() && !self.is_done() {
+            observer::notify(TimerEvent::Cancelled(self.state.armed()));
+        }
+    }
+}
+
+impl Drop for Timer {
+    fn drop(&mut self) {
+        self.cancelled();
+
+        let handle = unsafe { ptr::read(&*self.handle) };
+
+        match handle {
+            // only the thread that created a timer can reuse it - see `TimerPool`.
+            Handle::Native(mut handle) => {
+                ACTIVE_TIMERS.fetch_sub(1, Relaxed);
+
+                if thread::current().id() == self.owner {
+                    handle.disarm();
+                    self.state.recycle();
+
+                    TimerPool::give(self.clock, handle, self.state.clone());
+                }
+            }
+            #[cfg(feature = "wheel")]
+            Handle::Wheel(_) => {}
+            Handle::Mock(_) => {}
+        }
+    }
+}
+
+/// Returns the finest granularity the native timers on this platform can fire at.
+///
+/// Delays are rounded up to a multiple of this, so that they never fire early, which
+/// makes a delay much shorter than it pointless - a 100us delay on Windows, say, still
+/// waits for the next tick of the system clock. This is a
+/// nanosecond or so on Linux and macOS, a millisecond in the browser and on the BSDs
+/// other than FreeBSD, and the system clock tick (usually 15.6ms) on Windows.
+///
+/// Timers driven by a `TimerWheel` go by its tick instead.
+pub fn min_resolution() -> Duration {
+    imp::resolution()
+}
+
+// how many live timers are backed by an os timer - kept up by `Timer`.
+static ACTIVE_TIMERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Returns how many os timers the process's delays, intervals and other timers hold
+/// right now.
+///
+/// This is a cheap gauge for spotting leaks and runaway timer use. Only timers that
+/// are alive count, armed or not - not the idle ones kept around for reuse after their
+/// timers were dropped, nor timers driven by a `TimerWheel` or a `MockTimer`.
+pub fn active_timer_count() -> usize {
+    ACTIVE_TIMERS.load(Relaxed)
+}
+
+/// Returns the time left until `deadline`, or zero if it has already passed.
+pub(crate) fn until(deadline: Instant) -> Duration {
+    let now = Instant::now();
+    if deadline > now {
+        deadline - now
+    } else {
+        Duration::from_secs(0)
+    }
+}
+
+/// Returns `at + by`, or as late an `Instant` as the platform can represent if that
+/// is out of range - `Instant` has no maximum to saturate at.
+pub(crate) fn saturating_add(at: Instant, by: Duration) -> Instant {
+    if let Some(later) = at.checked_add(by) {
+        return later;
+    }
+
+    // add as much of `by` as still fits, halving what's left every time it doesn't.
+    let mut at = at;
+    let mut step = by;
+    while step > Duration::from_secs(0) {
+        match at.checked_add(step) {
+            Some(later) => at = later,
+            None => step /= 2,
+        }
+    }
+
+    at
 }
 
 #[cfg(test)]
@@ -102,8 +765,26 @@ mod tests {
     use super::*;
     use futures::executor::block_on;
     use futures::prelude::*;
+    use futures::stream::StreamExt;
     use std::time::{Duration, Instant};
 
+    #[test]
+    fn send_and_sync() {
+        fn is_send_sync<T: Send + Sync>() {}
+
+        is_send_sync::<Delay>();
+        is_send_sync::<Interval>();
+        is_send_sync::<CountingInterval>();
+        is_send_sync::<TakeTicks>();
+        is_send_sync::<Backoff>();
+        is_send_sync::<Timeout<Delay>>();
+        is_send_sync::<Deadline>();
+        is_send_sync::<IdleBarrier>();
+        is_send_sync::<IdleHandle>();
+        is_send_sync::<MockTimer>();
+        is_send_sync::<Reactor>();
+    }
+
     #[test]
     fn join_timers() {
         use futures::join;
@@ -140,26 +821,233 @@ mod tests {
     }
 
     #[test]
-    fn intervals() {
-        use futures::select;
+    fn poll_through_executor() {
+        use futures::future::FusedFuture;
+        use futures::stream::FusedStream;
+
+        fn is_future<F: Future<Output = ()> + FusedFuture>(_: &F) {}
+        fn is_stream<S: Stream<Item = Instant> + FusedStream>(_: &S) {}
 
-        let mut timeout = Delay::new(Duration::from_secs(1));
-        let mut stream = Interval::new(Duration::from_millis(99));
+        let mut delay = Delay::new(Duration::from_millis(20));
+        let mut interval = Interval::new(Duration::from_millis(20));
+        is_future(&delay);
+        is_stream(&interval);
 
         let work = async {
-            let mut total = 0;
-            loop {
-                select! {
-                    _ = stream.next() => total += 1,
-                    _ = timeout => break,
-                }
+            await!(&mut delay);
+            await!(interval.next())
+        };
+
+        assert!(block_on(work).is_some());
+    }
+
+    #[test]
+    fn stale_expiries() {
+        let state = TimerState::new();
+
+        // an expiry of an arming that has since been cleared doesn't count.
+        state.arm(Due::after(Duration::from_secs(3600), None));
+        let generation = state.generation();
+        state.clear();
+        state.fire_for(generation, 1);
+        assert!(!state.done());
+
+        state.arm(Due::after(Duration::from_secs(3600), None));
+        state.fire_for(state.generation(), 1);
+        assert_eq!(state.take_ticks(), 1);
+
+        // without a generation, nothing counts before the arming can have expired...
+        state.fire_expired(Duration::from_secs(0));
+        assert!(!state.done());
+
+        // ...and once it has, each expiry after the first has to wait another period.
+        let period = Duration::from_secs(3600);
+        state.arm(Due::after(Duration::from_secs(0), Some(period)));
+        state.fire_expired(Duration::from_secs(0));
+        state.fire_expired(Duration::from_secs(0));
+        assert_eq!(state.take_ticks(), 1);
+    }
+
+    #[test]
+    fn changed_waker() {
+        use futures::task::ArcWake;
+        use std::pin::Pin;
+        use std::sync::atomic::Ordering::SeqCst;
+
+        struct Counter(AtomicUsize);
+
+        impl ArcWake for Counter {
+            fn wake(arc_self: &Arc<Self>) {
+                arc_self.0.fetch_add(1, SeqCst);
             }
+        }
 
-            total
-        };
+        let first = Arc::new(Counter(AtomicUsize::new(0)));
+        let second = Arc::new(Counter(AtomicUsize::new(0)));
+        let first_waker = ArcWake::into_waker(first.clone());
+        let second_waker = ArcWake::into_waker(second.clone());
 
-        let res = block_on(work);
-        assert_eq!(res, 10);
+        let mut interval = Interval::new(Duration::from_millis(30));
+
+        // polled from one task, then moved to another - only the new task gets woken.
+        assert!(Pin::new(&mut interval).poll_next(&first_waker).is_pending());
+        assert!(Pin::new(&mut interval)
+            .poll_next(&second_waker)
+            .is_pending());
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(first.0.load(SeqCst), 0);
+        assert_eq!(second.0.load(SeqCst), 1);
+
+        // the wakeup used up the registration, so the same waker has to be registered
+        // again to hear about the next tick.
+        assert!(Pin::new(&mut interval).poll_next(&second_waker).is_ready());
+        assert!(Pin::new(&mut interval)
+            .poll_next(&second_waker)
+            .is_pending());
+
+        thread::sleep(Duration::from_millis(50));
+        assert_eq!(first.0.load(SeqCst), 0);
+        assert_eq!(second.0.load(SeqCst), 2);
+    }
+
+    #[test]
+    fn changed_waker_on_mock() {
+        use futures::task::ArcWake;
+        use std::pin::Pin;
+        use std::sync::atomic::Ordering::SeqCst;
+
+        struct Counter(AtomicUsize);
+
+        impl ArcWake for Counter {
+            fn wake(arc_self: &Arc<Self>) {
+                arc_self.0.fetch_add(1, SeqCst);
+            }
+        }
+
+        let first = Arc::new(Counter(AtomicUsize::new(0)));
+        let second = Arc::new(Counter(AtomicUsize::new(0)));
+        let first_waker = ArcWake::into_waker(first.clone());
+        let second_waker = ArcWake::into_waker(second.clone());
+
+        let mock = MockTimer::new();
+        let mut interval = mock.interval(Duration::from_millis(30));
+
+        // polled from one task, then moved to another - only the new task gets woken.
+        assert!(Pin::new(&mut interval).poll_next(&first_waker).is_pending());
+        assert!(Pin::new(&mut interval)
+            .poll_next(&second_waker)
+            .is_pending());
+
+        mock.advance(Duration::from_millis(30));
+        assert_eq!(first.0.load(SeqCst), 0);
+        assert_eq!(second.0.load(SeqCst), 1);
+
+        // the wakeup used up the registration, so the same waker has to be registered
+        // again to hear about the next tick.
+        assert!(Pin::new(&mut interval).poll_next(&second_waker).is_ready());
+        assert!(Pin::new(&mut interval)
+            .poll_next(&second_waker)
+            .is_pending());
+
+        mock.advance(Duration::from_millis(30));
+        assert_eq!(first.0.load(SeqCst), 0);
+        assert_eq!(second.0.load(SeqCst), 2);
+    }
+
+    #[test]
+    fn huge_periods() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let huge = Duration::from_secs(u64::max_value());
+
+        let mut delay = compat::Delay::new(huge);
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        let mut interval = compat::Interval::new(huge);
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        let mut interval = Interval::with_jitter(huge, Duration::from_secs(1));
+        assert!(Pin::new(&mut interval)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+    }
+
+    #[test]
+    fn debug_output() {
+        let delay = Delay::new(Duration::from_secs(1));
+        let debug = format!("{:?}", delay);
+        assert!(debug.starts_with("Delay { delay: 1s, remaining: "));
+        assert!(debug.ends_with("armed: false, done: false }"));
+
+        let interval = Interval::new(Duration::from_millis(250));
+        let debug = format!("{:?}", interval);
+        assert_eq!(debug, "Interval { period: 250ms, armed: false }");
+    }
+
+    #[test]
+    fn fallible_timers() {
+        let delay = Delay::try_new(Duration::from_millis(20)).expect("delay");
+        let stream = Interval::try_new(Duration::from_millis(10)).expect("interval");
+
+        let start = Instant::now();
+        block_on(delay);
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+    }
+
+    #[test]
+    fn leeway_timers() {
+        let leeway = Duration::from_millis(10);
+        let start = Instant::now();
+
+        block_on(Delay::with_leeway(Duration::from_millis(20), leeway));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let stream = Interval::with_leeway(Duration::from_millis(10), leeway);
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn timer_resolution() {
+        let resolution = min_resolution();
+        assert!(resolution > Duration::from_secs(0));
+        assert!(resolution <= Duration::from_millis(100));
+
+        // a delay shorter than the resolution still waits, rather than rounding to zero.
+        let start = Instant::now();
+        block_on(Delay::new(resolution / 2));
+        assert!(start.elapsed() >= resolution / 2);
+    }
+
+    #[test]
+    fn count_active_timers() {
+        const TIMERS: usize = 2000;
+
+        let delays: Vec<_> = (0..TIMERS)
+            .map(|_| Delay::new(Duration::from_secs(60)))
+            .collect();
+        assert!(active_timer_count() >= TIMERS);
+
+        // other tests hold some timers of their own, but nowhere near as many.
+        drop(delays);
+        assert!(active_timer_count() < TIMERS);
+
+        // a mock timer holds no os timer.
+        let mock = MockTimer::new();
+        let before = active_timer_count();
+        let delays: Vec<_> = (0..TIMERS)
+            .map(|_| mock.delay(Duration::from_secs(1)))
+            .collect();
+        assert!(active_timer_count() < before + TIMERS);
+        drop(delays);
     }
 
     #[test]
@@ -206,6 +1094,111 @@ mod tests {
         assert_eq!(res, vec![1, 2, 3, 4, 5]);
     }
 
+    #[test]
+    fn drop_while_firing() {
+        // far more delays than the pool keeps, so most of them delete their os timer -
+        // right around when it fires, on this thread and on others.
+        for round in 0..20 {
+            let mut delays: Vec<_> = (0..200)
+                .map(|i| Delay::new(Duration::from_micros(500 + i % 7 * 100)))
+                .collect();
+
+            let waker = futures::task::noop_waker_ref();
+            for delay in &mut delays {
+                let _ = std::pin::Pin::new(delay).poll(waker);
+            }
+
+            thread::sleep(Duration::from_micros(800));
+            if round % 2 == 0 {
+                drop(delays);
+            } else {
+                thread::spawn(move || drop(delays)).join().unwrap();
+            }
+        }
+
+        // timers created afterwards still work.
+        block_on(Delay::new(Duration::from_millis(10)));
+    }
+
+    /// How many os timers the process holds right now, where the os lets us count them.
+    fn os_timer_count() -> Option<usize> {
+        use std::fs;
+
+        if cfg!(all(
+            target_os = "linux",
+            feature = "timerfd",
+            not(feature = "fallback")
+        )) {
+            let fds = fs::read_dir("/proc/self/fd").ok()?;
+            let timerfds = fds
+                .filter_map(|fd| fs::read_link(fd.ok()?.path()).ok())
+                .filter(|link| link.to_string_lossy().contains("timerfd"))
+                .count();
+            Some(timerfds)
+        } else if cfg!(all(target_os = "linux", not(feature = "fallback"))) {
+            // one `ID:` line per posix timer - missing on kernels without checkpoint/restore.
+            let timers = fs::read_to_string("/proc/self/timers").ok()?;
+            Some(
+                timers
+                    .lines()
+                    .filter(|line| line.starts_with("ID:"))
+                    .count(),
+            )
+        } else {
+            None
+        }
+    }
+
+    #[test]
+    fn no_leaked_timers() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        const TIMERS: usize = 5000;
+
+        let before = os_timer_count();
+
+        // on a thread of its own, so that its pool of idle timers goes away with it.
+        thread::spawn(|| {
+            let waker = noop_waker_ref();
+            for i in 0..TIMERS {
+                match i % 4 {
+                    // never armed.
+                    0 => drop(Delay::new(Duration::from_secs(60))),
+                    // armed, and dropped long before firing.
+                    1 => {
+                        let mut delay = Delay::new(Duration::from_secs(60));
+                        assert!(Pin::new(&mut delay).poll(waker).is_pending());
+                    }
+                    // armed, then disarmed again before being dropped.
+                    2 => {
+                        let mut interval = Interval::new(Duration::from_secs(60));
+                        assert!(Pin::new(&mut interval).poll_next(waker).is_pending());
+                        interval.stop();
+                    }
+                    // fired, and left that way.
+                    _ => block_on(Delay::new(Duration::from_micros(10))),
+                }
+            }
+        })
+        .join()
+        .unwrap();
+
+        // other tests create timers of their own at the same time, but nowhere near as
+        // many as a leak would leave behind.
+        if let (Some(before), Some(after)) = (before, os_timer_count()) {
+            assert!(
+                after < before + TIMERS / 5,
+                "{} os timers before, {} after",
+                before,
+                after
+            );
+        }
+
+        // timers created afterwards still work.
+        block_on(Delay::new(Duration::from_millis(10)));
+    }
+
     #[test]
     fn send_then_drop() {
         use futures::select;
@@ -228,11 +1221,26 @@ mod tests {
     }
 
     #[test]
-    fn timeout() {
-        use futures::future::empty;
+    #[cfg(not(all(
+        target_os = "linux",
+        not(any(feature = "signal-thread", feature = "timerfd", feature = "fallback"))
+    )))]
+    fn moved_delay() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+        use std::thread;
+
+        // armed on a thread that exits straight afterwards, then awaited on this one.
+        let delay = thread::spawn(|| {
+            let mut delay = Delay::new(Duration::from_millis(50));
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            delay
+        })
+        .join()
+        .unwrap();
 
-        // The empty future will always return Poll::Pending, so this will always timeout first
-        let result: Result<(), TimeoutError> = block_on(empty().timeout(Duration::new(0, 0)));
-        assert!(result.is_err());
+        let t = Instant::now();
+        block_on(delay);
+        assert!(t.elapsed() < Duration::from_millis(1000));
     }
 }
@@ -0,0 +1,298 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::{Delay, Interval, Timer, TimerSource, TimerState};
+
+/// A [`TimerSource`] whose clock only moves when [`MockTimer::advance`] is called.
+///
+/// Delays and intervals from a mock timer fire as soon as the mock clock passes their
+/// deadline, without anything sleeping, so tests can step through hours of timeouts
+/// straight away. The timer can be cloned and shared between threads, and every
+/// clone drives the same clock.
+///
+/// Only firing goes by the mock clock - methods like [`Delay::remaining`] and the
+/// `Instant`s an interval yields still use the real one.
+#[derive(Clone, Debug, Default)]
+pub struct MockTimer {
+    inner: Arc<MockClock>,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct MockClock {
+    timers: Mutex<MockTimers>,
+}
+
+#[derive(Debug, Default)]
+struct MockTimers {
+    // how far the clock has been advanced.
+    now: Duration,
+    pending: HashMap<u64, Entry>,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    // `None` for a deadline too far out to be a `Duration`, which never comes round.
+    deadline: Option<Duration>,
+    period: Option<Duration>,
+    state: Arc<TimerState>,
+    // the arming of the state the entry was inserted for.
+    generation: usize,
+}
+
+impl MockTimer {
+    pub fn new() -> Self {
+        MockTimer::default()
+    }
+
+    /// Moves the clock forward by `by`, firing everything that comes due.
+    pub fn advance(&self, by: Duration) {
+        self.inner.advance(by);
+    }
+
+    /// Returns how far the clock has been advanced in total.
+    pub fn elapsed(&self) -> Duration {
+        self.inner.timers.lock().unwrap().now
+    }
+
+    pub(crate) fn clock(&self) -> &Arc<MockClock> {
+        &self.inner
+    }
+}
+
+impl TimerSource for MockTimer {
+    type Delay = Delay;
+    type Interval = Interval;
+
+    fn delay(&self, delay: Duration) -> Delay {
+        Delay::from_timer(Timer::mocked(self), delay, None)
+    }
+
+    fn interval(&self, period: Duration) -> Interval {
+        Interval::from_timer(Timer::mocked(self), period, None)
+    }
+}
+
+impl MockClock {
+    fn insert(&self, state: Arc<TimerState>, start: Duration, period: Option<Duration>) -> u64 {
+        let mut timers = self.timers.lock().unwrap();
+        let id = timers.next_id;
+        timers.next_id += 1;
+
+        let entry = Entry {
+            deadline: timers.now.checked_add(start),
+            period,
+            generation: state.generation(),
+            state,
+        };
+        timers.pending.insert(id, entry);
+
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        self.timers.lock().unwrap().pending.remove(&id);
+    }
+
+    /// When every pending timer is next due, soonest first - leaving out any that never
+    /// will be.
+    #[allow(dead_code)]
+    pub(crate) fn deadlines(&self) -> Vec<Duration> {
+        let timers = self.timers.lock().unwrap();
+        let mut deadlines: Vec<_> = timers.pending.values().filter_map(|e| e.deadline).collect();
+
+        deadlines.sort();
+        deadlines
+    }
+
+    fn advance(&self, by: Duration) {
+        let mut fired = Vec::new();
+
+        {
+            let mut timers = self.timers.lock().unwrap();
+            // the clock stops at the last `Duration` rather than overflowing.
+            timers.now = timers.now.checked_add(by).unwrap_or(MAX);
+            let now = timers.now;
+
+            timers.pending.retain(|_, entry| {
+                let deadline = match entry.deadline {
+                    Some(deadline) if deadline <= now => deadline,
+                    _ => return true,
+                };
+
+                match entry.period {
+                    Some(period) if period > Duration::from_secs(0) => {
+                        // every period that fits between the deadline and now is a tick.
+                        let ticks = (now - deadline).as_nanos() / period.as_nanos() + 1;
+                        let count = ticks.min(usize::max_value() as u128) as usize;
+
+                        entry.deadline = later(deadline, period, ticks);
+                        fired.push((entry.state.clone(), entry.generation, count));
+                        true
+                    }
+                    _ => {
+                        fired.push((entry.state.clone(), entry.generation, 1));
+                        false
+                    }
+                }
+            });
+        }
+
+        // wake everything outside the lock, so woken tasks can re-arm straight away -
+        // the states drop the ticks of anything re-armed or disarmed in the meantime.
+        for (state, generation, count) in fired {
+            state.fire_for(generation, count);
+        }
+    }
+}
+
+// the furthest the mock clock can go.
+const MAX: Duration = Duration::new(u64::max_value(), 999_999_999);
+
+/// `at + period * times`, or `None` when that is past the last `Duration`.
+fn later(at: Duration, period: Duration, times: u128) -> Option<Duration> {
+    let nanos = period
+        .as_nanos()
+        .checked_mul(times)?
+        .checked_add(at.as_nanos())?;
+    let secs = nanos / 1_000_000_000;
+    if secs > u128::from(u64::max_value()) {
+        return None;
+    }
+
+    Some(Duration::new(secs as u64, (nanos % 1_000_000_000) as u32))
+}
+
+/// A timer registered with a [`MockTimer`], with the same interface as the
+/// `NativeTimer`s so it can stand in for one.
+#[derive(Debug)]
+pub(crate) struct MockHandle {
+    clock: Arc<MockClock>,
+    state: Arc<TimerState>,
+    id: Option<u64>,
+}
+
+impl MockHandle {
+    pub(crate) fn new(clock: Arc<MockClock>, state: Arc<TimerState>) -> Self {
+        MockHandle {
+            clock,
+            state,
+            id: None,
+        }
+    }
+
+    /// Creates another, unscheduled timer on the same clock.
+    pub(crate) fn sibling(&self, state: Arc<TimerState>) -> Self {
+        MockHandle::new(self.clock.clone(), state)
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.id.is_some()
+    }
+
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(delay, None);
+    }
+
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(start, Some(interval));
+    }
+
+    fn init(&mut self, start: Duration, period: Option<Duration>) {
+        self.disarm();
+        self.id = Some(self.clock.insert(self.state.clone(), start, period));
+    }
+
+    pub fn disarm(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.clock.remove(id);
+        }
+    }
+}
+
+impl Drop for MockHandle {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::NativeTimers;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use std::time::Instant;
+
+    #[test]
+    fn mock_timer() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        // generic code only sees a `TimerSource`.
+        fn wait<T: TimerSource>(timers: &T) -> T::Delay {
+            timers.delay(Duration::from_secs(60 * 60))
+        }
+
+        let mock = MockTimer::new();
+        let mut delay = wait(&mock);
+        let mut ticks = mock.interval(Duration::from_secs(60));
+
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(Pin::new(&mut ticks)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        mock.advance(Duration::from_secs(59 * 60));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(Pin::new(&mut ticks).poll_next(noop_waker_ref()).is_ready());
+        assert_eq!(ticks.missed_ticks(), 59);
+
+        mock.advance(Duration::from_secs(60));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_ready());
+        assert_eq!(mock.elapsed(), Duration::from_secs(60 * 60));
+
+        // the native source hands out real timers.
+        let start = Instant::now();
+        block_on(NativeTimers.delay(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn huge_delays_and_advances() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let mock = MockTimer::new();
+        mock.advance(Duration::from_secs(1));
+
+        // due past the last `Duration`, so never.
+        let mut delay = mock.delay(Duration::from_secs(u64::max_value()));
+        let mut ticks = mock.interval(Duration::from_secs(1));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        assert!(Pin::new(&mut ticks)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+
+        // more missed ticks than fit in a `u32`.
+        mock.advance(Duration::from_secs(u64::from(u32::max_value()) * 2));
+        assert!(Pin::new(&mut ticks).poll_next(noop_waker_ref()).is_ready());
+        assert_eq!(ticks.missed_ticks() as u64, u64::from(u32::max_value()) * 2);
+
+        // the clock stops at the end, and the interval's next tick is past it.
+        mock.advance(Duration::from_secs(u64::max_value()));
+        assert!(Pin::new(&mut ticks).poll_next(noop_waker_ref()).is_ready());
+        mock.advance(Duration::from_secs(u64::max_value()));
+        assert!(Pin::new(&mut ticks)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+    }
+}
@@ -0,0 +1,111 @@
+use std::ptr;
+use std::sync::atomic::AtomicPtr;
+use std::sync::atomic::Ordering::{AcqRel, Acquire};
+use std::time::Duration;
+
+/// Something that happened to a timer, as seen by the observer passed to
+/// `set_timer_observer`.
+///
+/// Each event carries the duration the timer was armed with - the delay of a delay,
+/// and the period of an interval.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum TimerEvent {
+    /// The timer was armed, or re-armed.
+    Armed(Duration),
+    /// The timer fired.
+    Fired(Duration),
+    /// The timer was disarmed, or dropped, before it fired.
+    Cancelled(Duration),
+}
+
+type Observer = Box<dyn Fn(TimerEvent) + Send + Sync>;
+
+static OBSERVER: AtomicPtr<Observer> = AtomicPtr::new(ptr::null_mut());
+
+/// Sets a function that is called for every timer that is armed, fires, or is
+/// cancelled, for exporting metrics like how many timers are running.
+///
+/// `Fired` events are sent from whichever thread fires the timer - usually a thread
+/// of the os's, or of this crate's - before the timer's task is woken. That thread
+/// fires other timers too, so the observer should be quick, and must never block:
+/// every timer it would have fired next waits for the observer to return.
+///
+/// Setting a new observer replaces the old one. Without an observer, each event
+/// costs a single atomic load.
+pub fn set_timer_observer<F>(observer: F)
+where
+    F: Fn(TimerEvent) + Send + Sync + 'static,
+{
+    let observer: Box<Observer> = Box::new(Box::new(observer));
+    let _old = OBSERVER.swap(Box::into_raw(observer), AcqRel);
+
+    // the old observer is leaked on purpose - a timer on another thread may still be
+    // in the middle of calling it, and there's no telling when it's done.
+}
+
+/// Passes `event` on to the observer, if there is one.
+pub(crate) fn notify(event: TimerEvent) {
+    let observer = OBSERVER.load(Acquire);
+    if !observer.is_null() {
+        unsafe { (*observer)(event) };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Delay, FutureExt as _, Interval};
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn timer_observer() {
+        use std::sync::Mutex;
+
+        // other tests run at the same time, so only look at timers with these durations.
+        let ours = [77, 78, 79]
+            .iter()
+            .map(|&ms| Duration::from_millis(ms))
+            .collect::<Vec<_>>();
+        let events = Arc::new(Mutex::new(Vec::new()));
+        {
+            let events = events.clone();
+            set_timer_observer(move |event| {
+                let duration = match event {
+                    TimerEvent::Armed(d) | TimerEvent::Fired(d) | TimerEvent::Cancelled(d) => d,
+                };
+                if ours.contains(&duration) {
+                    events.lock().unwrap().push(event);
+                }
+            });
+        }
+
+        let take_events = || events.lock().unwrap().drain(..).collect::<Vec<_>>();
+
+        block_on(Delay::new(Duration::from_millis(77)));
+        assert_eq!(
+            take_events(),
+            vec![
+                TimerEvent::Armed(Duration::from_millis(77)),
+                TimerEvent::Fired(Duration::from_millis(77)),
+            ]
+        );
+
+        let mut interval = Interval::new(Duration::from_millis(78));
+        block_on(interval.next());
+        drop(interval);
+        assert_eq!(
+            take_events(),
+            vec![
+                TimerEvent::Armed(Duration::from_millis(78)),
+                TimerEvent::Fired(Duration::from_millis(78)),
+                TimerEvent::Cancelled(Duration::from_millis(78)),
+            ]
+        );
+
+        let delay = Delay::new(Duration::from_millis(79));
+        let _ = block_on(delay.timeout(Duration::from_millis(10)));
+        assert!(take_events().contains(&TimerEvent::Cancelled(Duration::from_millis(79))));
+    }
+}
@@ -0,0 +1,170 @@
+use std::cell::RefCell;
+use std::sync::Arc;
+
+use super::imp::NativeTimer;
+use super::{Clock, TimerState};
+
+/// How many idle timers each thread keeps around.
+const MAX_IDLE: usize = 64;
+
+/// Idle native timers, kept around so that short-lived delays don't need a syscall to
+/// create and delete an os timer each.
+///
+/// The pool is per thread, since on linux without the `signal-thread` feature a
+/// timer's signals go to the thread that created it - so a handle can only be handed
+/// out again on that thread.
+pub(crate) struct TimerPool {
+    idle: Vec<(Clock, NativeTimer, Arc<TimerState>)>,
+}
+
+thread_local! {
+    static POOL: RefCell<TimerPool> = RefCell::new(TimerPool { idle: Vec::new() });
+}
+
+impl TimerPool {
+    /// Takes an idle timer measured against `clock` from this thread's pool.
+    pub(crate) fn take(clock: Clock) -> Option<(NativeTimer, Arc<TimerState>)> {
+        POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            let idx = pool.idle.iter().position(|&(c, ..)| c == clock)?;
+            let (_, handle, state) = pool.idle.swap_remove(idx);
+
+            Some((handle, state))
+        })
+        .ok()
+        .and_then(|timer| timer)
+    }
+
+    /// Takes up to `n` idle timers measured against `clock` from this thread's pool,
+    /// all in one go.
+    pub(crate) fn take_many(clock: Clock, n: usize) -> Vec<(NativeTimer, Arc<TimerState>)> {
+        POOL.try_with(|pool| {
+            let mut pool = pool.borrow_mut();
+            let mut taken = Vec::with_capacity(n);
+
+            let mut idx = 0;
+            while idx < pool.idle.len() && taken.len() < n {
+                if pool.idle[idx].0 == clock {
+                    let (_, handle, state) = pool.idle.swap_remove(idx);
+                    taken.push((handle, state));
+                } else {
+                    idx += 1;
+                }
+            }
+
+            taken
+        })
+        .unwrap_or_default()
+    }
+
+    /// Returns a disarmed timer to this thread's pool, or drops it if the pool is full
+    /// (or the thread is shutting down).
+    pub(crate) fn give(clock: Clock, handle: NativeTimer, state: Arc<TimerState>) {
+        let _ = POOL.try_with(move |pool| {
+            let mut pool = pool.borrow_mut();
+            if pool.idle.len() < MAX_IDLE {
+                pool.idle.push((clock, handle, state));
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Timer;
+    use crate::{Delay, Interval};
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use futures::task::ArcWake;
+    use std::time::Duration;
+    use std::time::Instant;
+
+    struct Noop;
+
+    impl ArcWake for Noop {
+        fn wake(_arc_self: &Arc<Self>) {}
+    }
+
+    #[test]
+    fn recycled_state() {
+        let task = Arc::new(Noop);
+        let waker = ArcWake::into_waker(task.clone());
+
+        let mut timer = Timer::new();
+        timer.register_waker(&waker);
+        timer.init_delay(Duration::from_secs(3600));
+        timer.state.fire();
+
+        let state = timer.state.clone();
+        let generation = state.generation();
+        drop((timer, waker));
+
+        // the next timer on this thread gets the same state back, with nothing of the
+        // old timer left in it.
+        let timer = Timer::new();
+        assert!(Arc::ptr_eq(&timer.state, &state));
+        assert_eq!(Arc::strong_count(&task), 1);
+        assert_eq!(state.wakes(), 0);
+        assert_eq!(state.armed(), Duration::from_secs(0));
+        assert!(!state.done());
+
+        // and an expiry of the old timer's arming still on its way doesn't count.
+        state.fire_for(generation, 1);
+        state.fire_expired(Duration::from_secs(0));
+        assert!(!state.done());
+    }
+
+    #[test]
+    fn reused_timers() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        // a fired delay and a running interval both go back to the pool here.
+        block_on(Delay::new(Duration::from_millis(10)));
+        let mut stream = Interval::new(Duration::from_millis(5));
+        block_on(stream.next());
+        drop(stream);
+
+        for _ in 0..2 {
+            let start = Instant::now();
+            let mut delay = Delay::new(Duration::from_millis(50));
+
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            block_on(delay);
+            assert!(start.elapsed() >= Duration::from_millis(50));
+        }
+    }
+
+    #[test]
+    fn tiny_delays_on_pool() {
+        use futures::executor::ThreadPool;
+        use futures::task::SpawnExt;
+        use std::sync::mpsc;
+
+        const NUM_TASKS: usize = 500;
+
+        let mut pool = ThreadPool::new().unwrap();
+        let (send, recv) = mpsc::channel();
+
+        // delays this short can fire before `poll` returns - none of the wakeups may
+        // get lost.
+        for i in 0..NUM_TASKS {
+            let send = send.clone();
+            let task = async move {
+                await!(Delay::new(Duration::from_nanos((i % 50) as u64 + 1)));
+                send.send(i).unwrap();
+            };
+            pool.spawn(task).unwrap();
+        }
+
+        let mut done = vec![false; NUM_TASKS];
+        for _ in 0..NUM_TASKS {
+            let i = recv
+                .recv_timeout(Duration::from_secs(10))
+                .expect("a delay never woke its task");
+            done[i] = true;
+        }
+        assert!(done.iter().all(|&done| done));
+    }
+}
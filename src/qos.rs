@@ -0,0 +1,27 @@
+/// How urgently the system should treat a timer's callbacks.
+///
+/// Only macOS uses this, to pick the dispatch queue a timer's event handler runs on -
+/// everywhere else it is ignored. The classes match the system's QoS classes, from the
+/// most to the least urgent.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum QosClass {
+    /// For work the user is interacting with directly, like keeping an animation
+    /// running.
+    UserInteractive,
+    /// For work the user started and is waiting on.
+    UserInitiated,
+    /// The system's default.
+    ///
+    /// This is the default.
+    Default,
+    /// For work whose results the user doesn't need straight away.
+    Utility,
+    /// For maintenance work the user isn't aware of.
+    Background,
+}
+
+impl Default for QosClass {
+    fn default() -> Self {
+        QosClass::Default
+    }
+}
@@ -0,0 +1,242 @@
+use std::io;
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "timerfd"),
+    not(feature = "fallback")
+))]
+use std::sync::Arc;
+use std::time::Duration;
+
+#[cfg(all(
+    target_os = "linux",
+    not(feature = "timerfd"),
+    not(feature = "fallback")
+))]
+use super::{imp, Clock, Handle, NativeTimer, TimerState};
+use super::{Delay, Interval, Timer, TimerSource};
+
+/// A thread that the delays and intervals created through it deliver their expiries
+/// to, whichever thread created them.
+///
+/// This only matters for posix timers on Linux, whose signals go to one particular
+/// thread - without the default `signal-thread` feature, the thread that created the
+/// timer, so that a timer created on an executor thread stops firing once that
+/// thread exits. A timer created by a reactor signals the reactor's thread instead,
+/// which lives as long as the process does, so it keeps firing wherever it is moved.
+///
+/// [`Reactor::new`] spawns a thread of its own, and [`Reactor::global`] hands out a
+/// shared one. With `signal-thread`, the shared reactor is the signal thread, so
+/// [`Delay::new`] and [`Interval::new`] already deliver to it. Everywhere else -
+/// other platforms, and the `timerfd` and `fallback` backends - timers never depended
+/// on the thread that created them, and a reactor creates ordinary ones.
+///
+/// Reactors are cheap handles: copying one shares its thread, and the thread keeps
+/// running after the last handle is gone, for the timers it already created.
+#[derive(Copy, Clone, Debug)]
+pub struct Reactor {
+    // the thread the timers signal.
+    #[cfg(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    ))]
+    tid: libc::c_int,
+    #[cfg(not(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    )))]
+    _private: (),
+}
+
+impl Reactor {
+    /// Spawns a new reactor thread - or, where timers don't need one, returns the
+    /// global reactor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the thread can't be spawned - see [`Reactor::try_new`] for a version
+    /// that returns the error instead.
+    pub fn new() -> Self {
+        Reactor::try_new().expect("error spawning timer reactor thread!")
+    }
+
+    /// Like [`Reactor::new`], but returns the os error if the thread can't be spawned.
+    #[cfg(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    ))]
+    pub fn try_new() -> io::Result<Self> {
+        Ok(Reactor {
+            tid: imp::spawn_reactor()?,
+        })
+    }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    )))]
+    pub fn try_new() -> io::Result<Self> {
+        Ok(Reactor::global())
+    }
+
+    /// Returns the reactor shared by the whole process, starting it if this is the
+    /// first time it is asked for.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the reactor thread has to be spawned, and can't be.
+    #[cfg(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    ))]
+    pub fn global() -> Self {
+        Reactor {
+            tid: imp::global_reactor(),
+        }
+    }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    )))]
+    pub fn global() -> Self {
+        Reactor { _private: () }
+    }
+
+    /// Creates a delay that fires `delay` after it is first polled, delivered to this
+    /// reactor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see [`Reactor::try_delay`] for a
+    /// version that returns the error instead.
+    pub fn delay(&self, delay: Duration) -> Delay {
+        self.try_delay(delay).expect("error creating native timer!")
+    }
+
+    /// Like [`Reactor::delay`], but returns the os error if the native timer can't be
+    /// created.
+    pub fn try_delay(&self, delay: Duration) -> io::Result<Delay> {
+        Ok(Delay::from_timer(self.timer()?, delay, None))
+    }
+
+    /// Creates an interval that ticks every `period`, delivered to this reactor.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the native timer can't be created - see [`Reactor::try_interval`] for
+    /// a version that returns the error instead.
+    pub fn interval(&self, period: Duration) -> Interval {
+        self.try_interval(period)
+            .expect("error creating native timer!")
+    }
+
+    /// Like [`Reactor::interval`], but returns the os error if the native timer can't
+    /// be created.
+    pub fn try_interval(&self, period: Duration) -> io::Result<Interval> {
+        Ok(Interval::from_timer(self.timer()?, period, None))
+    }
+
+    /// Creates an unarmed timer that signals this reactor.
+    #[cfg(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    ))]
+    fn timer(&self) -> io::Result<Timer> {
+        // always a new os timer - an idle one from the pool signals some other thread.
+        let clock = Clock::default();
+        let state = Arc::new(TimerState::new());
+        let handle = unsafe { NativeTimer::signalling(&state, clock, self.tid)? };
+
+        Ok(Timer::from_parts(Handle::Native(handle), state, clock))
+    }
+
+    #[cfg(not(all(
+        target_os = "linux",
+        not(feature = "timerfd"),
+        not(feature = "fallback")
+    )))]
+    fn timer(&self) -> io::Result<Timer> {
+        Timer::try_new(Default::default())
+    }
+}
+
+impl TimerSource for Reactor {
+    type Delay = Delay;
+    type Interval = Interval;
+
+    fn delay(&self, delay: Duration) -> Delay {
+        Reactor::delay(self, delay)
+    }
+
+    fn interval(&self, period: Duration) -> Interval {
+        Reactor::interval(self, period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use futures::task::noop_waker_ref;
+    use std::pin::Pin;
+    use std::thread;
+    use std::time::Instant;
+
+    #[test]
+    fn reactor_timers() {
+        let reactor = Reactor::new();
+
+        let start = Instant::now();
+        block_on(reactor.delay(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        let ticks = block_on(
+            reactor
+                .interval(Duration::from_millis(10))
+                .take(3)
+                .collect::<Vec<_>>(),
+        );
+        assert_eq!(ticks.len(), 3);
+    }
+
+    #[test]
+    fn global_reactor() {
+        let start = Instant::now();
+        block_on(Reactor::global().delay(Duration::from_millis(20)));
+        assert!(start.elapsed() >= Duration::from_millis(20));
+
+        // the same thread every time.
+        assert_eq!(
+            format!("{:?}", Reactor::global()),
+            format!("{:?}", Reactor::global())
+        );
+    }
+
+    #[test]
+    fn delay_from_exited_thread() {
+        let reactor = Reactor::new();
+
+        // created and armed on a thread that is gone by the time it fires, which
+        // doesn't matter to a timer that signals the reactor.
+        let delay = thread::spawn(move || {
+            let mut delay = reactor.delay(Duration::from_millis(20));
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            delay
+        })
+        .join()
+        .unwrap();
+
+        // well before the wake thread would give up on the expiry and fire it anyway.
+        let start = Instant::now();
+        block_on(delay);
+        assert!(start.elapsed() < Duration::from_millis(500));
+    }
+}
@@ -0,0 +1,37 @@
+use std::time::{Duration, Instant};
+
+use futures::prelude::*;
+
+use super::{Delay, Interval};
+
+/// Something that hands out delays and intervals.
+///
+/// Code that takes a `TimerSource` instead of calling [`Delay::new`] directly can be
+/// tested against a [`MockTimer`], whose clock only moves when it is told to.
+pub trait TimerSource {
+    type Delay: Future<Output = ()> + Unpin;
+    type Interval: Stream<Item = Instant> + Unpin;
+
+    /// Returns a future that completes after `delay`.
+    fn delay(&self, delay: Duration) -> Self::Delay;
+
+    /// Returns a stream that ticks every `period`.
+    fn interval(&self, period: Duration) -> Self::Interval;
+}
+
+/// The default [`TimerSource`], backed by native os timers.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct NativeTimers;
+
+impl TimerSource for NativeTimers {
+    type Delay = Delay;
+    type Interval = Interval;
+
+    fn delay(&self, delay: Duration) -> Delay {
+        Delay::new(delay)
+    }
+
+    fn interval(&self, period: Duration) -> Interval {
+        Interval::new(period)
+    }
+}
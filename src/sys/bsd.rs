@@ -0,0 +1,321 @@
+use super::{Clock, QosClass, TimerState};
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+use libc::{c_int, uintptr_t};
+
+// every timer is an `EVFILT_TIMER` event on one shared kqueue, which a single reactor
+// thread waits on - much like the `timerfd` backend on linux.
+
+/// The os timer behind a delay or interval - the timer's `ident` in the shared
+/// kqueue.
+pub type RawTimer = uintptr_t;
+
+// freebsd can take the timer data in nanoseconds, everyone else only does milliseconds.
+#[cfg(target_os = "freebsd")]
+const UNITS: u32 = libc::NOTE_NSECONDS;
+#[cfg(not(target_os = "freebsd"))]
+const UNITS: u32 = 0;
+
+struct Reactor {
+    kqueue: c_int,
+    // keyed by `ident`, so that events for timers that have since been dropped can be
+    // told apart.
+    timers: Mutex<HashMap<uintptr_t, Registration>>,
+}
+
+struct Registration {
+    state: Arc<TimerState>,
+    // the arming of the state the timer was last added for - every event carries the
+    // one it was added with in its `udata`, so that events of an earlier arming, which
+    // the reactor may already have taken off the kqueue, can be told apart.
+    generation: usize,
+    // the period to switch to after the first expiration, for intervals whose first
+    // tick isn't one period away.
+    then: Option<Duration>,
+}
+
+/// What a timer does after it first fires. Intervals whose first tick isn't one
+/// period away start off as a one-shot, which the reactor swaps for the periodic timer.
+#[derive(Copy, Clone)]
+enum Then {
+    Stop,
+    Periodic(Duration),
+}
+
+static REACTOR_INIT: Once = Once::new();
+static mut REACTOR: *const Reactor = ptr::null();
+
+static NEXT_IDENT: AtomicUsize = AtomicUsize::new(1);
+
+fn reactor() -> &'static Reactor {
+    REACTOR_INIT.call_once(|| unsafe {
+        let kqueue = libc::kqueue();
+        assert!(kqueue >= 0, "error creating timer kqueue!");
+
+        REACTOR = Box::into_raw(Box::new(Reactor {
+            kqueue,
+            timers: Mutex::new(HashMap::new()),
+        }));
+
+        thread::Builder::new()
+            .name("futures-native-timers".into())
+            .spawn(|| reactor().run())
+            .expect("error spawning timer reactor thread!");
+    });
+
+    unsafe { &*REACTOR }
+}
+
+impl Reactor {
+    fn register(&self, ident: uintptr_t, state: Arc<TimerState>) {
+        let registration = Registration {
+            generation: state.generation(),
+            state,
+            then: None,
+        };
+
+        self.timers.lock().unwrap().insert(ident, registration);
+    }
+
+    fn deregister(&self, ident: uintptr_t) {
+        self.timers.lock().unwrap().remove(&ident);
+    }
+
+    // arming and disarming happen under the lock, so the reactor can't swap in a
+    // periodic timer behind them.
+
+    fn arm(&self, ident: uintptr_t, start: Duration, then: Then) {
+        let mut timers = self.timers.lock().unwrap();
+        let generation = match timers.get_mut(&ident) {
+            Some(registration) => {
+                registration.generation = registration.state.generation();
+                registration.then = match then {
+                    Then::Periodic(period) if period != start => Some(period),
+                    _ => None,
+                };
+
+                registration.generation
+            }
+            None => return,
+        };
+
+        let mut flags = libc::EV_ADD | libc::EV_ENABLE;
+        match then {
+            // a periodic kqueue timer first fires one period after it is added.
+            Then::Periodic(period) if period == start => {}
+            _ => flags |= libc::EV_ONESHOT,
+        }
+
+        // adding an existing timer again replaces it. Like `timer_settime` on linux,
+        // this can only fail if the kernel has no room for the timer.
+        let res = self.change(ident, flags as u16, to_data(start), generation);
+        if res < 0 {
+            panic!("error arming native timer! {}", io::Error::last_os_error());
+        }
+    }
+
+    fn disarm(&self, ident: uintptr_t) {
+        let mut timers = self.timers.lock().unwrap();
+        if let Some(registration) = timers.get_mut(&ident) {
+            registration.then = None;
+        }
+
+        // a one-shot timer that already fired is gone, so this can fail harmlessly.
+        self.change(ident, libc::EV_DELETE as u16, 0, 0);
+    }
+
+    fn change(&self, ident: uintptr_t, flags: u16, data: i64, generation: usize) -> c_int {
+        unsafe {
+            let mut change: libc::kevent = mem::zeroed();
+            change.ident = ident;
+            change.filter = libc::EVFILT_TIMER as _;
+            change.flags = flags as _;
+            change.fflags = UNITS as _;
+            change.data = data as _;
+            change.udata = generation as _;
+
+            libc::kevent(self.kqueue, &change, 1, ptr::null_mut(), 0, ptr::null())
+        }
+    }
+
+    fn run(&self) {
+        let mut events: [libc::kevent; 64] = unsafe { mem::zeroed() };
+
+        loop {
+            let n = unsafe {
+                libc::kevent(
+                    self.kqueue,
+                    ptr::null(),
+                    0,
+                    events.as_mut_ptr(),
+                    events.len() as c_int,
+                    ptr::null(),
+                )
+            };
+
+            // interrupted - just try again.
+            if n < 0 {
+                continue;
+            }
+
+            for event in &events[..n as usize] {
+                let generation = event.udata as usize;
+
+                let state = {
+                    let mut timers = self.timers.lock().unwrap();
+                    timers.get_mut(&event.ident).map(|registration| {
+                        // the first tick of an interval that starts off schedule - switch
+                        // over to the periodic timer, unless the timer has been re-armed
+                        // since.
+                        if registration.generation == generation {
+                            if let Some(period) = registration.then.take() {
+                                let flags = libc::EV_ADD | libc::EV_ENABLE;
+                                let data = to_data(period);
+                                self.change(event.ident, flags as u16, data, generation);
+                            }
+                        }
+
+                        registration.state.clone()
+                    })
+                };
+
+                // fired outside the lock - an event of an arming that has since ended is
+                // dropped by the state.
+                if let Some(state) = state {
+                    dbg_println!("handled - {:p}", &*state);
+                    state.fire_for(generation, event.data.max(1) as usize);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeTimer {
+    ident: uintptr_t,
+    active: bool,
+}
+
+impl NativeTimer {
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
+        if clock == Clock::Realtime {
+            warn_realtime();
+        }
+
+        // creating the reactor is the only part that can fail, and it panics instead, so
+        // there's no os error to hand back here.
+        let reactor = reactor();
+
+        // the reactor holds its own reference, which keeps the state alive for as long
+        // as the timer is registered.
+        let ident = NEXT_IDENT.fetch_add(1, Ordering::SeqCst);
+        reactor.register(ident, state.clone());
+
+        Ok(NativeTimer {
+            ident,
+            active: false,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn raw(&self) -> RawTimer {
+        self.ident
+    }
+
+    /// kqueue timers have no notion of leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    /// Arms the timer to fire once, `delay` from now.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kqueue won't take the timer - see `init_interval_at`.
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(delay, Then::Stop);
+    }
+
+    /// Arms the timer to fire every `interval`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kqueue won't take the timer - see `init_interval_at`.
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    /// Arms the timer to fire `start` from now, and every `interval` after that.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the kqueue won't take the timer, which only happens when the kernel
+    /// can't allocate it.
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(start, Then::Periodic(interval));
+    }
+
+    fn init(&mut self, start: Duration, then: Then) {
+        dbg_println!("created timer!");
+        self.active = true;
+
+        reactor().arm(self.ident, start, then);
+    }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+        reactor().disarm(self.ident);
+    }
+}
+
+/// The unit `EVFILT_TIMER` events are given in.
+pub(crate) fn resolution() -> Duration {
+    if UNITS == 0 {
+        Duration::from_millis(1)
+    } else {
+        Duration::from_nanos(1)
+    }
+}
+
+/// Converts a duration into the `data` of an `EVFILT_TIMER` event, rounding up.
+fn to_data(duration: Duration) -> i64 {
+    let data = if UNITS == 0 {
+        (duration.as_nanos() + 999_999) / 1_000_000
+    } else {
+        duration.as_nanos()
+    };
+
+    // zero means "as soon as possible" on some bsds, and is rejected on others.
+    data.max(1).min(i64::max_value() as u128) as i64
+}
+
+/// kqueue timers only measure the monotonic clock, so warn (once) when asked for
+/// anything else.
+fn warn_realtime() {
+    static WARNED: Once = Once::new();
+
+    WARNED.call_once(|| {
+        eprintln!(
+            "warning: futures-native-timers can't use the realtime clock on bsd, \
+             falling back to the monotonic clock"
+        );
+    });
+}
+
+impl Drop for NativeTimer {
+    fn drop(&mut self) {
+        self.disarm();
+        reactor().deregister(self.ident);
+    }
+}
@@ -0,0 +1,237 @@
+use super::{Clock, QosClass, TimerState};
+use std::io;
+use std::sync::{Arc, Mutex, Once};
+use std::thread::{self, Thread};
+use std::time::{Duration, Instant};
+
+// a portable backend for platforms without one of their own: every timer gets a
+// thread of its own, which parks until the next deadline.
+//
+// this costs a thread per timer, but only needs `std`.
+
+/// The os timer behind a delay or interval - the thread that drives it.
+pub type RawTimer = Thread;
+
+#[derive(Debug)]
+struct Schedule {
+    next: Option<Instant>,
+    period: Option<Duration>,
+    // the arming of the state the schedule was set for.
+    generation: usize,
+    closed: bool,
+}
+
+#[derive(Debug)]
+pub struct NativeTimer {
+    schedule: Arc<Mutex<Schedule>>,
+    state: Arc<TimerState>,
+    thread: Thread,
+    active: bool,
+}
+
+impl NativeTimer {
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
+        if clock == Clock::Realtime {
+            warn_realtime();
+        }
+
+        // the thread holds its own reference, which keeps the state alive for as long as
+        // it is running.
+        let shared = state.clone();
+
+        let schedule = Arc::new(Mutex::new(Schedule {
+            next: None,
+            period: None,
+            generation: state.generation(),
+            closed: false,
+        }));
+
+        let thread = {
+            let schedule = schedule.clone();
+
+            thread::Builder::new()
+                .name("futures-native-timers".into())
+                .spawn(move || run(&schedule, &shared))?
+                .thread()
+                .clone()
+        };
+
+        Ok(NativeTimer {
+            schedule,
+            state: state.clone(),
+            thread,
+            active: false,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn raw(&self) -> RawTimer {
+        self.thread.clone()
+    }
+
+    /// Parked threads have no notion of leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(delay, None);
+    }
+
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(start, Some(interval));
+    }
+
+    fn init(&mut self, start: Duration, period: Option<Duration>) {
+        dbg_println!("created timer!");
+        self.active = true;
+
+        {
+            let mut schedule = self.schedule.lock().unwrap();
+
+            // a deadline too far out to represent never comes.
+            schedule.next = Instant::now().checked_add(start);
+            schedule.period = period;
+            schedule.generation = self.state.generation();
+        }
+
+        self.thread.unpark();
+    }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+
+        let mut schedule = self.schedule.lock().unwrap();
+        schedule.next = None;
+        schedule.period = None;
+    }
+}
+
+fn run(schedule: &Mutex<Schedule>, state: &TimerState) {
+    loop {
+        let mut schedule = schedule.lock().unwrap();
+        if schedule.closed {
+            break;
+        }
+
+        let next = match schedule.next {
+            Some(next) => next,
+            None => {
+                drop(schedule);
+                thread::park();
+                continue;
+            }
+        };
+
+        let now = Instant::now();
+        if now < next {
+            drop(schedule);
+            // may wake early, or because the timer was re-armed - either way the
+            // schedule is looked at again.
+            thread::park_timeout(next - now);
+            continue;
+        }
+
+        let mut count = 1;
+        schedule.next = schedule.period.and_then(|period| {
+            let mut next = next.checked_add(period)?;
+            while next <= now {
+                next = next.checked_add(period)?;
+                count += 1;
+            }
+
+            Some(next)
+        });
+
+        // fired outside the lock, so the woken task (or the observer) can re-arm or
+        // disarm the timer straight away - the state drops the tick if it does so first.
+        let generation = schedule.generation;
+        drop(schedule);
+
+        dbg_println!("handled - {:p}", state);
+        state.fire_for(generation, count);
+    }
+}
+
+/// How finely a parked thread can wait - to the nanosecond on unix, but only in whole
+/// milliseconds on windows.
+pub(crate) fn resolution() -> Duration {
+    if cfg!(windows) {
+        Duration::from_millis(1)
+    } else {
+        Duration::from_nanos(1)
+    }
+}
+
+/// A parked thread only measures the monotonic clock, so warn (once) when asked for
+/// anything else.
+fn warn_realtime() {
+    static WARNED: Once = Once::new();
+
+    WARNED.call_once(|| {
+        eprintln!(
+            "warning: futures-native-timers can't use the realtime clock with the fallback \
+             backend, falling back to the monotonic clock"
+        );
+    });
+}
+
+impl Drop for NativeTimer {
+    fn drop(&mut self) {
+        self.disarm();
+        self.schedule.lock().unwrap().closed = true;
+        self.thread.unpark();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Delay;
+    use futures::prelude::*;
+    use futures::task::ArcWake;
+    use std::pin::Pin;
+    use std::sync::mpsc;
+
+    #[test]
+    fn rearm_from_wakeup() {
+        struct Rearm {
+            delay: Mutex<Option<Delay>>,
+            woken: Mutex<mpsc::Sender<()>>,
+        }
+
+        impl ArcWake for Rearm {
+            fn wake(arc_self: &Arc<Self>) {
+                // re-arming takes the schedule lock of the thread that is waking us.
+                if let Some(delay) = arc_self.delay.lock().unwrap().as_mut() {
+                    delay.reset(Duration::from_secs(5));
+                }
+
+                let _ = arc_self.woken.lock().unwrap().send(());
+            }
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let task = Arc::new(Rearm {
+            delay: Mutex::new(None),
+            woken: Mutex::new(tx),
+        });
+        let waker = ArcWake::into_waker(task.clone());
+
+        let mut delay = Delay::new(Duration::from_millis(50));
+        assert!(Pin::new(&mut delay).poll(&waker).is_pending());
+        *task.delay.lock().unwrap() = Some(delay);
+
+        rx.recv_timeout(Duration::from_secs(2)).unwrap();
+        let delay = task.delay.lock().unwrap().take().unwrap();
+        assert!(delay.remaining() > Duration::from_secs(4));
+    }
+}
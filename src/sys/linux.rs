@@ -1,20 +1,27 @@
 #![allow(non_camel_case_types)]
 
-use super::TimerState;
+use super::{until, Clock, QosClass, TimerError, TimerState};
+use std::collections::HashMap;
+use std::fmt;
+use std::io;
 use std::mem;
 use std::ptr;
-use std::sync::Once;
-use std::time::Duration;
+use std::sync::{mpsc, Arc, Mutex, Once};
+use std::thread;
+use std::time::{Duration, Instant};
 
 use libc::{
-    c_int, c_void, clockid_t, itimerspec, sigaction, sigevent, siginfo_t, suseconds_t, time_t,
-    timespec, CLOCK_MONOTONIC,
+    c_int, c_long, c_void, clockid_t, itimerspec, sigaction, sigevent, siginfo_t, time_t, timespec,
+    CLOCK_MONOTONIC, CLOCK_REALTIME,
 };
 
 // for some reason these aren't in the libc crate yet.
 
 type timer_t = usize;
 
+/// The os timer behind a delay or interval - a posix `timer_t`.
+pub type RawTimer = timer_t;
+
 extern "C" {
     fn timer_create(clockid: clockid_t, sevp: *mut sigevent, timerid: *mut timer_t) -> c_int;
     fn timer_settime(
@@ -23,26 +30,97 @@ extern "C" {
         new_value: *const itimerspec,
         old_value: *mut itimerspec,
     ) -> c_int;
+    fn timer_gettime(timerid: timer_t, curr_value: *mut itimerspec) -> c_int;
     fn timer_delete(timerid: timer_t);
+
+    // glibc reserves a few real-time signals for itself, so the range has to be
+    // asked for at runtime.
+    fn __libc_current_sigrtmin() -> c_int;
+    fn __libc_current_sigrtmax() -> c_int;
 }
 
 // set up the signal handler
-// FIXME: find a free real-time signal properly
 static HANDLER: Once = Once::new();
-const MYSIG: c_int = 40;
+
+// the real-time signal the handler was installed on, picked once by `init_handler`.
+static mut SIGNAL: c_int = 0;
 
 unsafe fn init_handler() {
+    let signal = match find_free_signal() {
+        Some(signal) => signal,
+        None => panic!(
+            "error creating timer signal handler: every real-time signal \
+             (SIGRTMIN..=SIGRTMAX) already has a handler installed!"
+        ),
+    };
+
     let mut sa: sigaction = mem::zeroed();
     sa.sa_flags = libc::SA_SIGINFO;
     sa.sa_sigaction = handler as usize;
     libc::sigemptyset(&mut sa.sa_mask);
 
-    if sigaction(MYSIG, &sa, ptr::null_mut()) == -1 {
+    if sigaction(signal, &sa, ptr::null_mut()) == -1 {
         panic!("error creating timer sigal handler!");
     }
+
+    dbg_println!("using signal {}", signal);
+    SIGNAL = signal;
 }
 
+/// Finds the first real-time signal that is still set to its default disposition,
+/// so that we don't steal a signal the rest of the application is using.
+unsafe fn find_free_signal() -> Option<c_int> {
+    (__libc_current_sigrtmin()..=__libc_current_sigrtmax()).find(|&signal| {
+        let mut current: sigaction = mem::zeroed();
+
+        sigaction(signal, ptr::null(), &mut current) == 0 && current.sa_sigaction == libc::SIG_DFL
+    })
+}
+
+// the handler runs in signal context, where almost nothing is safe to call - not
+// `AtomicWaker::wake`, and certainly not whatever an executor's waker does. so the
+// handler never wakes anything itself: timer signals are turned into wakeups on a
+// thread of our own instead, either
+//
+// - by the signal thread (the default `signal-thread` feature), which every timer
+//   signals directly. it keeps the signal blocked and picks it up with `sigwaitinfo`,
+//   so the handler never even runs, and timers keep firing after the thread that
+//   created them has exited.
+// - or without it, by a wake thread. signals still go to the thread that created the
+//   timer, and the handler only forwards the timer's key down a pipe - `write` being
+//   async-signal-safe - for the wake thread to fire.
+//
+// a signal for a thread that has exited is never delivered at all, and one for a timer
+// disarmed through its raw handle never sent - so either thread also keeps an eye on
+// the one-shot timers it is waiting for, and fires any that ran out without a signal
+// ever arriving for them.
+
+#[cfg(feature = "signal-thread")]
+unsafe extern "C" fn handler(_sig: c_int, _si: *mut siginfo_t, _uc: *mut c_void) {
+    // never runs - the signal is only ever sent to the signal thread, which has it
+    // blocked. installing a handler still marks the signal as taken.
+}
+
+#[cfg(not(feature = "signal-thread"))]
 unsafe extern "C" fn handler(_sig: c_int, si: *mut siginfo_t, _uc: *mut c_void) {
+    // no `dbg_println!` (or anything else that isn't async-signal-safe) in here.
+    let errno = *libc::__errno_location();
+
+    // the pipe holds thousands of keys and is drained as fast as they arrive, so it
+    // never fills up in practice - and if it ever does, the signal is dropped rather
+    // than blocking inside the handler.
+    let key = timer_state(si) as usize;
+    libc::write(
+        WAKE_PIPE,
+        &key as *const usize as *const c_void,
+        mem::size_of::<usize>(),
+    );
+
+    *libc::__errno_location() = errno;
+}
+
+/// Pulls the `TimerState` pointer the timer was created with out of a `siginfo_t`.
+unsafe fn timer_state(si: *const siginfo_t) -> *mut TimerState {
     // evil things are afoot - tread wisely.
     //
     // the `libc` crate exposes the union part of siginfo_t as a array of i32s,
@@ -51,78 +129,497 @@ unsafe extern "C" fn handler(_sig: c_int, si: *mut siginfo_t, _uc: *mut c_void)
     let raw_bytes = (*si)._pad;
     let val: libc::sigval = ptr::read(raw_bytes[3..].as_ptr() as *const _);
 
-    let state = val.sival_ptr as *mut TimerState;
-    dbg_println!("handled - {:p}", state);
+    val.sival_ptr as *mut TimerState
+}
+
+/// How long after a one-shot timer ran out its signal has to be missing before the
+/// expiry counts as lost.
+const GRACE: Duration = Duration::from_secs(1);
+
+/// The thread that turns timer signals into wakeups.
+struct WakeThread {
+    // the thread every timer signals.
+    #[cfg(feature = "signal-thread")]
+    tid: c_int,
+    // signals can still be queued for a timer that has just been deleted, so the
+    // thread only fires states that are still registered here.
+    timers: &'static Mutex<Timers>,
+}
+
+struct Timers {
+    registered: HashMap<usize, Registration>,
+    // the soonest `overdue` of any registration, as of the wake thread's last look -
+    // it doesn't need to look again before then.
+    next_check: Option<Instant>,
+}
+
+struct Registration {
+    state: Arc<TimerState>,
+    timer: timer_t,
+    // for a one-shot timer whose signal hasn't arrived yet, when it counts as lost,
+    // and the arming of the state that was for.
+    overdue: Option<(Instant, usize)>,
+}
+
+static WAKE_THREAD_INIT: Once = Once::new();
+static mut WAKE_THREAD: *const WakeThread = ptr::null();
+
+// the write end of the wake thread's pipe, for the handler.
+#[cfg(not(feature = "signal-thread"))]
+static mut WAKE_PIPE: c_int = -1;
+
+/// Installs the signal handler and starts the wake thread, if that hasn't happened
+/// yet.
+fn init() -> &'static WakeThread {
+    HANDLER.call_once(|| unsafe { init_handler() });
+    wake_thread()
+}
+
+fn wake_thread() -> &'static WakeThread {
+    WAKE_THREAD_INIT.call_once(|| unsafe {
+        let timers: &'static Mutex<_> = Box::leak(Box::new(Mutex::new(Timers {
+            registered: HashMap::new(),
+            next_check: None,
+        })));
+        let (tx, rx) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("futures-native-timers".into())
+            .spawn(move || run_wake_thread(timers, tx))
+            .expect("error spawning timer wake thread!");
+
+        let _tid = rx.recv().expect("timer wake thread died during startup!");
+        WAKE_THREAD = Box::into_raw(Box::new(WakeThread {
+            #[cfg(feature = "signal-thread")]
+            tid: _tid,
+            timers,
+        }));
+    });
+
+    unsafe { &*WAKE_THREAD }
+}
+
+impl WakeThread {
+    fn register(&self, state: &Arc<TimerState>, timer: timer_t) {
+        // the wake thread holds its own reference, which keeps the state alive for as
+        // long as the timer is registered.
+        let key = state.as_ptr() as usize;
+        let registration = Registration {
+            state: state.clone(),
+            timer,
+            overdue: None,
+        };
+
+        self.timers
+            .lock()
+            .unwrap()
+            .registered
+            .insert(key, registration);
+    }
+
+    fn deregister(&self, state: usize) {
+        self.timers.lock().unwrap().registered.remove(&state);
+    }
+
+    /// Starts watching for the expiry of a one-shot timer that is due after `delay`.
+    fn watch(&self, state: usize, delay: Duration) {
+        let overdue = match overdue_after(Instant::now(), delay) {
+            Some(overdue) => overdue,
+            // it's never going to run out.
+            None => return self.unwatch(state),
+        };
+
+        let mut timers = self.timers.lock().unwrap();
+        if let Some(registration) = timers.registered.get_mut(&state) {
+            registration.overdue = Some((overdue, registration.state.generation()));
+        }
+
+        // the thread is waiting for something later, or for nothing at all.
+        if timers.next_check.map_or(true, |next| overdue < next) {
+            timers.next_check = Some(overdue);
+            drop(timers);
+            self.kick();
+        }
+    }
+
+    fn unwatch(&self, state: usize) {
+        let mut timers = self.timers.lock().unwrap();
+        if let Some(registration) = timers.registered.get_mut(&state) {
+            registration.overdue = None;
+        }
+    }
+
+    /// Wakes the thread up early, so it looks at `next_check` again.
+    #[cfg(feature = "signal-thread")]
+    fn kick(&self) {
+        unsafe {
+            libc::syscall(libc::SYS_tgkill, libc::getpid(), self.tid, SIGNAL);
+        }
+    }
+
+    #[cfg(not(feature = "signal-thread"))]
+    fn kick(&self) {
+        // no timer is ever registered under 0.
+        let key = 0usize;
+        unsafe {
+            libc::write(
+                WAKE_PIPE,
+                &key as *const usize as *const c_void,
+                mem::size_of::<usize>(),
+            );
+        }
+    }
+}
+
+/// Fires the timer registered under `key`, if it still is.
+///
+/// A signal carries nothing but the key, so one from before the timer was last
+/// disarmed or re-armed looks just like a new one. The state tells them apart by
+/// whether the current arming can already have expired.
+fn fire_registered(timers: &Mutex<Timers>, key: usize) {
+    let state = match timers.lock().unwrap().registered.get(&key) {
+        Some(registration) => registration.state.clone(),
+        None => return,
+    };
+
+    dbg_println!("handled - {:p}", &*state);
+    if !state.fire_expired(Duration::from_secs(0)) {
+        return;
+    }
+
+    // it arrived, so there's no need to watch for it any more - unless the timer has
+    // been re-armed in the meantime, and what is watched for is the next expiry.
+    let mut timers = timers.lock().unwrap();
+    if let Some(registration) = timers.registered.get_mut(&key) {
+        if let Some((_, generation)) = registration.overdue {
+            if generation == state.generation() {
+                registration.overdue = None;
+            }
+        }
+    }
+}
+
+/// When the expiry of a timer due `delay` after `now` counts as lost, if it ever can.
+fn overdue_after(now: Instant, delay: Duration) -> Option<Instant> {
+    now.checked_add(delay.checked_add(GRACE)?)
+}
+
+/// Fires every watched timer that has run out without its signal arriving, if it is
+/// time to look, and returns when to look next.
+fn fire_lost(timers: &Mutex<Timers>) -> Option<Instant> {
+    let now = Instant::now();
+    let mut lost = Vec::new();
+
+    let next = {
+        let mut timers = timers.lock().unwrap();
+        match timers.next_check {
+            Some(next) if next <= now => {}
+            next => return next,
+        }
+
+        let mut next: Option<Instant> = None;
+        for registration in timers.registered.values_mut() {
+            let (overdue, generation) = match registration.overdue {
+                Some(overdue) => overdue,
+                None => continue,
+            };
+
+            if overdue > now {
+                next = Some(next.map_or(overdue, |next| next.min(overdue)));
+                continue;
+            }
+
+            let mut curr: itimerspec = unsafe { mem::zeroed() };
+            if unsafe { timer_gettime(registration.timer, &mut curr) } != 0 {
+                registration.overdue = None;
+                continue;
+            }
+
+            // a zero `it_value` means the timer isn't going to expire again - and
+            // otherwise it was re-armed through its raw handle, so keep waiting.
+            let left = Duration::new(curr.it_value.tv_sec as u64, curr.it_value.tv_nsec as u32);
+            if left == Duration::from_secs(0) {
+                registration.overdue = None;
+                lost.push(registration.state.clone());
+            } else if let Some(overdue) = overdue_after(now, left) {
+                registration.overdue = Some((overdue, generation));
+                next = Some(next.map_or(overdue, |next| next.min(overdue)));
+            } else {
+                registration.overdue = None;
+            }
+        }
+
+        timers.next_check = next;
+        next
+    };
+
+    // the state drops the expiry if it did arrive after all.
+    for state in lost {
+        dbg_println!("lost - {:p}", &*state);
+        state.fire_expired(Duration::from_secs(0));
+    }
+
+    next
+}
+
+/// Waits for the timer signals itself, once it has sent back its thread id for the
+/// timers to signal.
+#[cfg(feature = "signal-thread")]
+fn run_wake_thread(timers: &Mutex<Timers>, tid: mpsc::Sender<c_int>) {
+    unsafe {
+        let set = block_signal();
+        let _ = tid.send(libc::syscall(libc::SYS_gettid) as c_int);
+
+        let mut next_check = None;
+        loop {
+            let mut si: siginfo_t = mem::zeroed();
+
+            let signal = match next_check {
+                Some(next) => {
+                    let timeout = to_timespec(until(next));
+                    libc::sigtimedwait(&set, &mut si, &timeout)
+                }
+                None => libc::sigwaitinfo(&set, &mut si),
+            };
+
+            // a signal from `kick` rather than a timer, or interrupted or timed out -
+            // either way, only the lost expiries need looking at.
+            if signal == SIGNAL && si.si_code != libc::SI_TKILL {
+                fire_registered(timers, timer_state(&si) as usize);
+            }
+
+            next_check = fire_lost(timers);
+        }
+    }
+}
+
+/// Blocks the timer signal on the calling thread, so it stays queued for
+/// `sigwaitinfo`, and returns the set holding just that signal.
+unsafe fn block_signal() -> libc::sigset_t {
+    let mut set: libc::sigset_t = mem::zeroed();
+    libc::sigemptyset(&mut set);
+    libc::sigaddset(&mut set, SIGNAL);
+
+    let res = libc::pthread_sigmask(libc::SIG_BLOCK, &set, ptr::null_mut());
+    assert_eq!(res, 0);
+
+    set
+}
+
+/// Spawns a reactor thread for timers to signal, and returns its thread id.
+///
+/// A reactor thread only picks up timer signals - the wake thread still watches for
+/// the expiries that never arrive.
+pub(crate) fn spawn_reactor() -> io::Result<c_int> {
+    let timers = init().timers;
+    let (tx, rx) = mpsc::channel();
+
+    thread::Builder::new()
+        .name("futures-native-timers-reactor".into())
+        .spawn(move || run_reactor(timers, tx))?;
+
+    rx.recv()
+        .map_err(|_| io::Error::new(io::ErrorKind::Other, "timer reactor died during startup"))
+}
+
+/// The thread id of the reactor that `Reactor::global` hands out - the signal thread,
+/// which every timer signals anyway.
+#[cfg(feature = "signal-thread")]
+pub(crate) fn global_reactor() -> c_int {
+    init().tid
+}
+
+/// The thread id of the reactor that `Reactor::global` hands out, spawned the first
+/// time it is asked for.
+#[cfg(not(feature = "signal-thread"))]
+pub(crate) fn global_reactor() -> c_int {
+    static GLOBAL_REACTOR: Once = Once::new();
+    static mut GLOBAL_TID: c_int = 0;
 
-    (*state).set_done(true);
-    (*state).wake.wake();
+    GLOBAL_REACTOR.call_once(|| unsafe {
+        GLOBAL_TID = spawn_reactor().expect("error spawning timer reactor thread!");
+    });
+
+    unsafe { GLOBAL_TID }
+}
+
+fn run_reactor(timers: &Mutex<Timers>, tid: mpsc::Sender<c_int>) {
+    unsafe {
+        let set = block_signal();
+        let _ = tid.send(libc::syscall(libc::SYS_gettid) as c_int);
+
+        loop {
+            let mut si: siginfo_t = mem::zeroed();
+
+            // interrupted otherwise - there's nothing else for a reactor to look at.
+            let signal = libc::sigwaitinfo(&set, &mut si);
+            if signal == SIGNAL && si.si_code != libc::SI_TKILL {
+                fire_registered(timers, timer_state(&si) as usize);
+            }
+        }
+    }
+}
+
+/// Reads the keys the handler forwards down the pipe, once it has set the pipe up.
+#[cfg(not(feature = "signal-thread"))]
+fn run_wake_thread(timers: &Mutex<Timers>, ready: mpsc::Sender<c_int>) {
+    // every write is a single key, well under `PIPE_BUF`, so reads never see part of
+    // one.
+    const KEY_SIZE: usize = mem::size_of::<usize>();
+
+    unsafe {
+        let mut fds = [0; 2];
+        let res = libc::pipe2(fds.as_mut_ptr(), libc::O_CLOEXEC);
+        assert_eq!(res, 0, "error creating timer wake pipe!");
+
+        // only the handler's end is non-blocking.
+        let res = libc::fcntl(fds[1], libc::F_SETFL, libc::O_NONBLOCK);
+        assert_eq!(res, 0);
+
+        WAKE_PIPE = fds[1];
+        let _ = ready.send(0);
+
+        let mut next_check: Option<Instant> = None;
+        loop {
+            // in whole milliseconds, rounded up so as not to wake before it's time.
+            let timeout = next_check.map_or(-1, |next| {
+                let millis = (until(next).as_nanos() + 999_999) / 1_000_000;
+                millis.min(c_int::max_value() as u128) as c_int
+            });
+
+            let mut pollfd = libc::pollfd {
+                fd: fds[0],
+                events: libc::POLLIN,
+                revents: 0,
+            };
+
+            // interrupted or timed out - only the lost expiries need looking at.
+            if libc::poll(&mut pollfd, 1, timeout) > 0 {
+                let mut key = 0usize;
+                let n = libc::read(fds[0], &mut key as *mut usize as *mut c_void, KEY_SIZE);
+
+                if n == KEY_SIZE as isize {
+                    fire_registered(timers, key);
+                }
+            }
+
+            next_check = fire_lost(timers);
+        }
+    }
 }
 
-#[derive(Debug)]
 pub struct NativeTimer {
     inner: timer_t,
     active: bool,
+    // the key the timer is registered under with the wake thread.
+    state: usize,
+}
+
+// the os handle is just a pointer, so leave it out.
+impl fmt::Debug for NativeTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeTimer")
+            .field("active", &self.active)
+            .finish()
+    }
 }
 
+// the timer calls are safe from any thread, so moving a timer is sound - but its
+// signals keep going to the thread that created it (or the signal thread), which is a
+// question of whether it fires at all, not of memory safety. `timer_t` is really a
+// pointer, so spell this out rather than relying on the `usize` above.
+unsafe impl Send for NativeTimer {}
+unsafe impl Sync for NativeTimer {}
+
 impl NativeTimer {
-    pub(crate) unsafe fn new(state: *mut TimerState) -> Self {
-        HANDLER.call_once(|| init_handler());
-        dbg_println!("{:p}", state);
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
+        init();
+        NativeTimer::signalling(state, clock, notify_thread_id())
+    }
 
-        let sival_ptr = state as *mut _;
+    /// Creates a timer whose signals go to the thread `tid` - the signal thread, a
+    /// reactor, or the thread creating it.
+    pub(crate) unsafe fn signalling(
+        state: &Arc<TimerState>,
+        clock: Clock,
+        tid: c_int,
+    ) -> io::Result<Self> {
+        // started before the timer exists, so it is there for its first signal.
+        let wake_thread = init();
+        dbg_println!("{:p}", state.as_ptr());
+
+        let sival_ptr = state.as_ptr() as *mut _;
         let mut sev: sigevent = mem::zeroed();
         sev.sigev_value = libc::sigval { sival_ptr };
-        sev.sigev_signo = MYSIG;
+        sev.sigev_signo = SIGNAL;
 
         // yes, this means that if you create a timer on a thread that later is dropped,
         // timer events won't fire. changing this to SIGEV_SIGNAL leads to complete
         // non-deterministic behavior when running tests, since any thread could be
         // interupted for any signal.
         //
-        // this is unfortunate, but will do for now - generally futures executors don't
-        // tend to kill and respawn threads often.
-        //
-        // a solution to this might have to involve a dedicated thread for signal handling.
+        // so by default the `signal-thread` feature sends every signal to a dedicated
+        // thread instead - this is only what happens without it, or a `Reactor`.
         sev.sigev_notify = libc::SIGEV_THREAD_ID;
-        let tid = libc::syscall(libc::SYS_gettid);
-        sev.sigev_notify_thread_id = tid as i32;
+        sev.sigev_notify_thread_id = tid;
 
         let mut timer = 0;
-        let res = timer_create(CLOCK_MONOTONIC, &mut sev, &mut timer);
-        assert_eq!(res, 0);
+        retry_interrupted(|| timer_create(clock_id(clock), &mut sev, &mut timer)).map_err(
+            |err| match err.raw_os_error() {
+                // each timer reserves a queued signal, which counts against the limit.
+                Some(libc::EAGAIN) => TimerError::LimitReached.into(),
+                _ => err,
+            },
+        )?;
 
-        NativeTimer {
+        wake_thread.register(state, timer);
+
+        Ok(NativeTimer {
             inner: timer,
             active: false,
-        }
+            state: state.as_ptr() as usize,
+        })
     }
 
     pub fn is_active(&self) -> bool {
         self.active
     }
 
-    pub fn init_delay(&mut self, delay: Duration) {
-        let ticks = timespec {
-            tv_sec: delay.as_secs() as time_t,
-            tv_nsec: delay.subsec_nanos() as suseconds_t,
-        };
+    pub fn raw(&self) -> RawTimer {
+        self.inner
+    }
+
+    /// Posix timers have no notion of leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
 
-        self.init(ticks, None);
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(to_timespec(delay), None);
+        wake_thread().watch(self.state, delay);
     }
 
     pub fn init_interval(&mut self, interval: Duration) {
-        let ticks = timespec {
-            tv_sec: interval.as_secs() as time_t,
-            tv_nsec: interval.subsec_nanos() as suseconds_t,
-        };
+        self.init_interval_at(interval, interval);
+    }
 
-        self.init(ticks, Some(ticks));
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        // an interval's signals keep coming, so there's no one expiry to watch for.
+        wake_thread().unwatch(self.state);
+        self.init(to_timespec(start), Some(to_timespec(interval)));
     }
 
-    fn init(&mut self, start: timespec, repeat: Option<timespec>) {
+    fn init(&mut self, mut start: timespec, repeat: Option<timespec>) {
         dbg_println!("created timer!");
         self.active = true;
 
+        // a zero `it_value` disarms the timer instead of firing it, so fire as soon as
+        // possible instead.
+        if start.tv_sec == 0 && start.tv_nsec == 0 {
+            start.tv_nsec = 1;
+        }
+
         let repeat = repeat.unwrap_or(timespec {
             tv_sec: 0,
             tv_nsec: 0,
@@ -133,17 +630,194 @@ impl NativeTimer {
             it_value: start,
         };
 
-        unsafe {
-            let res = timer_settime(self.inner, 0, &new_value, ptr::null_mut());
-            assert_eq!(res, 0);
+        let res = retry_interrupted(|| unsafe {
+            timer_settime(self.inner, 0, &new_value, ptr::null_mut())
+        });
+        res.expect("error arming native timer!");
+    }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+
+        // stop watching first, so the stopped timer isn't taken for a lost expiry.
+        wake_thread().unwatch(self.state);
+
+        // a zeroed `it_value` stops the timer without deleting it.
+        let stop: itimerspec = unsafe { mem::zeroed() };
+        let res =
+            retry_interrupted(|| unsafe { timer_settime(self.inner, 0, &stop, ptr::null_mut()) });
+        res.expect("error disarming native timer!");
+    }
+}
+
+/// How many times a timer syscall is retried after being interrupted by a signal,
+/// before the interruption is treated as an error.
+const MAX_RETRIES: usize = 16;
+
+/// Runs a timer syscall that returns 0 on success, retrying it if a signal interrupts
+/// it, and returns any other error.
+fn retry_interrupted<F: FnMut() -> c_int>(mut syscall: F) -> io::Result<()> {
+    let mut retries = 0;
+
+    loop {
+        if syscall() == 0 {
+            return Ok(());
+        }
+
+        let err = io::Error::last_os_error();
+        if err.kind() != io::ErrorKind::Interrupted || retries == MAX_RETRIES {
+            return Err(err);
         }
+
+        retries += 1;
     }
 }
 
+/// The resolution of the monotonic clock timers are measured against - how finely the
+/// kernel can place their expirations.
+pub(crate) fn resolution() -> Duration {
+    let mut res: timespec = unsafe { mem::zeroed() };
+    if unsafe { libc::clock_getres(CLOCK_MONOTONIC, &mut res) } != 0 {
+        // only fails for clocks the kernel doesn't have.
+        return Duration::from_nanos(1);
+    }
+
+    Duration::new(res.tv_sec as u64, res.tv_nsec as u32)
+}
+
+fn clock_id(clock: Clock) -> clockid_t {
+    match clock {
+        Clock::Monotonic => CLOCK_MONOTONIC,
+        Clock::Realtime => CLOCK_REALTIME,
+    }
+}
+
+fn to_timespec(duration: Duration) -> timespec {
+    timespec {
+        // saturate rather than wrapping around to a negative time.
+        tv_sec: duration.as_secs().min(time_t::max_value() as u64) as time_t,
+        tv_nsec: duration.subsec_nanos() as c_long,
+    }
+}
+
+/// The thread a new timer's signals should go to.
+#[cfg(not(feature = "signal-thread"))]
+unsafe fn notify_thread_id() -> c_int {
+    libc::syscall(libc::SYS_gettid) as c_int
+}
+
+#[cfg(feature = "signal-thread")]
+unsafe fn notify_thread_id() -> c_int {
+    wake_thread().tid
+}
+
 impl Drop for NativeTimer {
     fn drop(&mut self) {
+        // deregistered first, so the wake thread never looks at a deleted timer. a signal
+        // that was already queued still arrives after `timer_delete`, which the wake
+        // thread ignores once the timer is no longer registered.
+        wake_thread().deregister(self.state);
+
         unsafe {
             timer_delete(self.inner);
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fail_with(errno: c_int) -> c_int {
+        unsafe {
+            *libc::__errno_location() = errno;
+        }
+
+        -1
+    }
+
+    #[test]
+    fn lost_expiry() {
+        use crate::Delay;
+        use futures::executor::block_on;
+        use futures::prelude::*;
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let mut delay = Delay::new(Duration::from_millis(10));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+
+        // disarm the os timer behind the delay's back, so the expiry never arrives.
+        unsafe {
+            let stop: itimerspec = mem::zeroed();
+            let timer = delay.raw_handle().unwrap();
+            assert_eq!(timer_settime(timer, 0, &stop, ptr::null_mut()), 0);
+        }
+
+        // the wake thread notices, and wakes the task instead of leaving it hanging.
+        let start = Instant::now();
+        block_on(delay);
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn delay_from_exited_thread() {
+        use crate::Delay;
+        use futures::executor::block_on;
+        use futures::prelude::*;
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        // armed on a thread that is gone by the time it fires - without the signal
+        // thread, its signal goes nowhere.
+        let delay = thread::spawn(|| {
+            let mut delay = Delay::new(Duration::from_millis(20));
+            assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+            delay
+        })
+        .join()
+        .unwrap();
+
+        let start = Instant::now();
+        block_on(delay);
+        assert!(start.elapsed() < Duration::from_secs(3));
+    }
+
+    #[test]
+    fn retries_interrupted_syscalls() {
+        let mut calls = 0;
+        let res = retry_interrupted(|| {
+            calls += 1;
+            if calls < 4 {
+                fail_with(libc::EINTR)
+            } else {
+                0
+            }
+        });
+
+        assert!(res.is_ok());
+        assert_eq!(calls, 4);
+    }
+
+    #[test]
+    fn gives_up_on_other_errors() {
+        let mut calls = 0;
+        let res = retry_interrupted(|| {
+            calls += 1;
+            fail_with(libc::EINVAL)
+        });
+
+        assert_eq!(res.unwrap_err().raw_os_error(), Some(libc::EINVAL));
+        assert_eq!(calls, 1);
+
+        // interruptions that never stop are eventually given up on too.
+        let mut calls = 0;
+        let res = retry_interrupted(|| {
+            calls += 1;
+            fail_with(libc::EINTR)
+        });
+
+        assert_eq!(res.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert_eq!(calls, MAX_RETRIES + 1);
+    }
+}
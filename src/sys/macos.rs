@@ -1,9 +1,13 @@
 #![allow(non_camel_case_types)]
 
-use super::TimerState;
+use super::{Clock, QosClass, TimerState};
+use std::fmt;
+use std::io;
+use std::ptr;
+use std::sync::Arc;
 use std::time::Duration;
 
-use libc::{c_long, c_ulong, c_void, int64_t, uint64_t, uintptr_t};
+use libc::{c_int, c_long, c_ulong, c_void, int64_t, timespec, uint64_t, uintptr_t};
 
 type dispatch_object_t = *const c_void;
 type dispatch_queue_t = *const c_void;
@@ -11,12 +15,27 @@ type dispatch_source_t = *const c_void;
 type dispatch_source_type_t = *const c_void;
 type dispatch_time_t = uint64_t;
 
+/// The os timer behind a delay or interval - a dispatch timer source.
+pub type RawTimer = dispatch_source_t;
+
 const DISPATCH_TIME_NOW: dispatch_time_t = 0;
+const QOS_CLASS_USER_INTERACTIVE: c_long = 0x21;
+const QOS_CLASS_USER_INITIATED: c_long = 0x19;
 const QOS_CLASS_DEFAULT: c_long = 0x15;
+const QOS_CLASS_UTILITY: c_long = 0x11;
+const QOS_CLASS_BACKGROUND: c_long = 0x09;
+
+#[repr(C)]
+struct mach_timebase_info_data_t {
+    numer: u32,
+    denom: u32,
+}
 
 extern "C" {
     static _dispatch_source_type_timer: c_long;
 
+    fn mach_timebase_info(info: *mut mach_timebase_info_data_t) -> c_int;
+
     fn dispatch_get_global_queue(identifier: c_long, flags: c_ulong) -> dispatch_queue_t;
     fn dispatch_source_create(
         type_: dispatch_source_type_t,
@@ -34,74 +53,185 @@ extern "C" {
         source: dispatch_source_t,
         handler: unsafe extern "C" fn(*mut c_void),
     );
+    fn dispatch_source_set_cancel_handler_f(
+        source: dispatch_source_t,
+        handler: unsafe extern "C" fn(*mut c_void),
+    );
+    fn dispatch_source_cancel(source: dispatch_source_t);
     fn dispatch_set_context(object: dispatch_object_t, context: *mut c_void);
+    fn dispatch_set_target_queue(object: dispatch_object_t, queue: dispatch_queue_t);
     fn dispatch_resume(object: dispatch_object_t);
+    fn dispatch_suspend(object: dispatch_object_t);
     fn dispatch_release(object: dispatch_object_t);
     fn dispatch_time(when: dispatch_time_t, delta: int64_t) -> dispatch_time_t;
+    fn dispatch_walltime(when: *const timespec, delta: int64_t) -> dispatch_time_t;
 }
 
-#[derive(Debug)]
 pub struct NativeTimer {
     timer: dispatch_source_t,
     active: bool,
+    leeway: uint64_t,
+    clock: Clock,
+    qos: QosClass,
 }
 
+// the os handle is just a pointer, so leave it out.
+impl fmt::Debug for NativeTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeTimer")
+            .field("active", &self.active)
+            .field("leeway", &self.leeway)
+            .field("clock", &self.clock)
+            .field("qos", &self.qos)
+            .finish()
+    }
+}
+
+// dispatch sources can be used from any thread, and nothing here is touched through a
+// shared reference.
 unsafe impl Send for NativeTimer {}
+unsafe impl Sync for NativeTimer {}
 
 impl NativeTimer {
-    pub(crate) unsafe fn new(state: *mut TimerState) -> Self {
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
         let timer = dispatch_source_create(
             &_dispatch_source_type_timer as *const _ as dispatch_source_type_t,
             0, // handle (not used for timers)
             0, // mask (ditto)
-            dispatch_get_global_queue(QOS_CLASS_DEFAULT, 0),
+            dispatch_get_global_queue(qos_class_id(QosClass::default()), 0),
         );
 
+        if timer.is_null() {
+            return Err(io::Error::last_os_error());
+        }
+
+        // the source holds its own reference to the state, which the cancel handler
+        // releases once the last event handler has returned.
         dispatch_source_set_event_handler_f(timer, handler);
-        dispatch_set_context(timer, state as *mut _);
+        dispatch_source_set_cancel_handler_f(timer, release_state);
+        dispatch_set_context(timer, Arc::into_raw(state.clone()) as *mut _);
 
-        NativeTimer {
+        Ok(NativeTimer {
             timer,
             active: false,
-        }
+            leeway: 0,
+            clock,
+            qos: QosClass::default(),
+        })
     }
 
     pub fn is_active(&self) -> bool {
         self.active
     }
 
-    pub fn init_delay(&mut self, delay: Duration) {
+    pub fn raw(&self) -> RawTimer {
+        self.timer
+    }
+
+    /// How late the system is allowed to fire the timer, so it can coalesce wakeups.
+    pub fn set_leeway(&mut self, leeway: Duration) {
+        self.leeway = to_nanos(leeway);
+    }
+
+    /// Moves the timer's event handler onto the global queue for `qos`.
+    pub fn set_qos_class(&mut self, qos: QosClass) {
+        if qos == self.qos {
+            return;
+        }
+
         unsafe {
-            dispatch_source_set_timer(
-                self.timer,
-                dispatch_time(DISPATCH_TIME_NOW, delay.as_nanos() as int64_t),
-                0, // interval
-                0, // leeway
-            );
-            dispatch_resume(self.timer);
+            dispatch_set_target_queue(self.timer, dispatch_get_global_queue(qos_class_id(qos), 0));
         }
 
-        self.active = true;
+        self.qos = qos;
+    }
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(to_delta(delay), 0);
     }
 
     pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(to_delta(start), to_nanos(interval));
+    }
+
+    fn init(&mut self, start: int64_t, interval: uint64_t) {
         unsafe {
-            dispatch_source_set_timer(
-                self.timer,
-                dispatch_time(DISPATCH_TIME_NOW, interval.as_nanos() as int64_t),
-                interval.as_nanos() as uint64_t,
-                0, // leeway
-            );
-            dispatch_resume(self.timer);
+            // dispatch has no clock to pick, but a wall clock start time makes the
+            // source follow the system time instead.
+            let start = match self.clock {
+                Clock::Monotonic => dispatch_time(DISPATCH_TIME_NOW, start),
+                Clock::Realtime => dispatch_walltime(ptr::null(), start),
+            };
+
+            dispatch_source_set_timer(self.timer, start, interval, self.leeway);
+
+            // re-arming an already running source only needs the new timer values -
+            // resuming it a second time would unbalance the suspend count.
+            if !self.active {
+                dispatch_resume(self.timer);
+            }
         }
 
         self.active = true;
     }
+
+    pub fn disarm(&mut self) {
+        // suspending doesn't interrupt a handler that is already running, but no new
+        // ones are delivered until the source is resumed by the next `init`.
+        if self.active {
+            unsafe {
+                dispatch_suspend(self.timer);
+            }
+
+            self.active = false;
+        }
+    }
+}
+
+/// The length of a tick of the clock dispatch times are kept in - dispatch takes
+/// nanoseconds, but rounds them to whole ticks.
+pub(crate) fn resolution() -> Duration {
+    let mut info = mach_timebase_info_data_t { numer: 0, denom: 0 };
+    if unsafe { mach_timebase_info(&mut info) } != 0 || info.denom == 0 {
+        return Duration::from_nanos(1);
+    }
+
+    // one tick is `numer / denom` nanoseconds - rounded up, as a tick is never shorter.
+    let nanos = (u64::from(info.numer) + u64::from(info.denom) - 1) / u64::from(info.denom);
+    Duration::from_nanos(nanos.max(1))
+}
+
+fn qos_class_id(qos: QosClass) -> c_long {
+    match qos {
+        QosClass::UserInteractive => QOS_CLASS_USER_INTERACTIVE,
+        QosClass::UserInitiated => QOS_CLASS_USER_INITIATED,
+        QosClass::Default => QOS_CLASS_DEFAULT,
+        QosClass::Utility => QOS_CLASS_UTILITY,
+        QosClass::Background => QOS_CLASS_BACKGROUND,
+    }
+}
+
+/// Converts a duration into a `dispatch_time` delta, saturating instead of wrapping
+/// around for durations over ~292 years.
+fn to_delta(duration: Duration) -> int64_t {
+    duration.as_nanos().min(int64_t::max_value() as u128) as int64_t
+}
+
+fn to_nanos(duration: Duration) -> uint64_t {
+    duration.as_nanos().min(uint64_t::max_value() as u128) as uint64_t
 }
 
 impl Drop for NativeTimer {
     fn drop(&mut self) {
         unsafe {
+            // a handler may still be running on another thread - cancelling delivers no
+            // new ones, and only releases the state after it is done.
+            dispatch_source_cancel(self.timer);
+
             // The timer starts in a suspended state, and: "It is important to balance
             // calls to dispatch_suspend and dispatch_resume so that the dispatch object
             // is fully resumed when the last reference is released. The behavior when
@@ -119,6 +249,12 @@ impl Drop for NativeTimer {
 unsafe extern "C" fn handler(context: *mut c_void) {
     let state = context as *mut TimerState;
 
-    (*state).set_done(true);
-    (*state).wake.wake();
+    // suspending the source doesn't stop a handler that was already on its way, which
+    // can then run after the timer has been re-armed - the state drops it unless the
+    // new arming can have expired by now.
+    (*state).fire_expired(Duration::from_secs(0));
+}
+
+unsafe extern "C" fn release_state(context: *mut c_void) {
+    drop(Arc::from_raw(context as *const TimerState));
 }
@@ -0,0 +1,269 @@
+use super::{Clock, QosClass, TimerState};
+use std::collections::HashMap;
+use std::io;
+use std::mem;
+use std::ptr;
+use std::sync::{Arc, Mutex, Once};
+use std::thread;
+use std::time::Duration;
+
+use libc::{
+    c_int, c_long, c_void, clockid_t, itimerspec, time_t, timespec, CLOCK_MONOTONIC, CLOCK_REALTIME,
+};
+
+// an alternative to the signal based backend: every timer is a `timerfd`, and a single
+// reactor thread waits on all of them with epoll.
+//
+// this means the native callbacks never run in a signal handler, and timers keep
+// firing no matter which thread created them or whether it is still alive.
+
+/// The os timer behind a delay or interval - a `timerfd` file descriptor.
+pub type RawTimer = c_int;
+
+struct Reactor {
+    epoll: c_int,
+    timers: Mutex<HashMap<c_int, Registration>>,
+}
+
+struct Registration {
+    state: Arc<TimerState>,
+    // the arming of the state the timer was last set for.
+    generation: usize,
+}
+
+static REACTOR_INIT: Once = Once::new();
+static mut REACTOR: *const Reactor = ptr::null();
+
+fn reactor() -> &'static Reactor {
+    REACTOR_INIT.call_once(|| unsafe {
+        let epoll = libc::epoll_create1(libc::EPOLL_CLOEXEC);
+        assert!(epoll >= 0, "error creating timer epoll instance!");
+
+        REACTOR = Box::into_raw(Box::new(Reactor {
+            epoll,
+            timers: Mutex::new(HashMap::new()),
+        }));
+
+        thread::Builder::new()
+            .name("futures-native-timers".into())
+            .spawn(|| reactor().run())
+            .expect("error spawning timer reactor thread!");
+    });
+
+    unsafe { &*REACTOR }
+}
+
+impl Reactor {
+    fn register(&self, fd: c_int, state: Arc<TimerState>) -> io::Result<()> {
+        let generation = state.generation();
+        let registration = Registration { state, generation };
+        self.timers.lock().unwrap().insert(fd, registration);
+
+        let mut event = libc::epoll_event {
+            events: libc::EPOLLIN as u32,
+            u64: fd as u64,
+        };
+
+        unsafe {
+            if libc::epoll_ctl(self.epoll, libc::EPOLL_CTL_ADD, fd, &mut event) != 0 {
+                let err = io::Error::last_os_error();
+                self.timers.lock().unwrap().remove(&fd);
+                return Err(err);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn deregister(&self, fd: c_int) {
+        unsafe {
+            libc::epoll_ctl(self.epoll, libc::EPOLL_CTL_DEL, fd, ptr::null_mut());
+        }
+
+        self.timers.lock().unwrap().remove(&fd);
+    }
+
+    // setting the timer, and reading its expirations, both happen under the lock - so
+    // the expirations the reactor reads always go with the arming it records for them.
+
+    fn set(&self, fd: c_int, new_value: &itimerspec) {
+        let mut timers = self.timers.lock().unwrap();
+        if let Some(registration) = timers.get_mut(&fd) {
+            registration.generation = registration.state.generation();
+        }
+
+        unsafe {
+            let res = libc::timerfd_settime(fd, 0, new_value, ptr::null_mut());
+            assert_eq!(res, 0);
+        }
+    }
+
+    fn run(&self) {
+        let mut events: [libc::epoll_event; 64] = unsafe { mem::zeroed() };
+
+        loop {
+            let n = unsafe {
+                libc::epoll_wait(self.epoll, events.as_mut_ptr(), events.len() as c_int, -1)
+            };
+
+            // interrupted - just try again.
+            if n < 0 {
+                continue;
+            }
+
+            for event in &events[..n as usize] {
+                let fd = event.u64 as c_int;
+
+                let fired = {
+                    let timers = self.timers.lock().unwrap();
+
+                    // the timer may have been disarmed (or dropped) since epoll saw it
+                    // fire, in which case the read fails and there is nothing to do.
+                    let mut expirations: u64 = 0;
+                    let res = unsafe {
+                        libc::read(
+                            fd,
+                            &mut expirations as *mut u64 as *mut c_void,
+                            mem::size_of::<u64>(),
+                        )
+                    };
+
+                    if res != mem::size_of::<u64>() as isize {
+                        continue;
+                    }
+
+                    timers.get(&fd).map(|registration| {
+                        let state = registration.state.clone();
+                        (state, registration.generation, expirations)
+                    })
+                };
+
+                // fired outside the lock - the timer can be re-armed in the meantime, and
+                // the state then drops these expirations as belonging to the old arming.
+                if let Some((state, generation, expirations)) = fired {
+                    dbg_println!("handled - {:p}", &*state);
+                    state.fire_for(generation, expirations as usize);
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct NativeTimer {
+    fd: c_int,
+    active: bool,
+}
+
+impl NativeTimer {
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
+        let fd = libc::timerfd_create(clock_id(clock), libc::TFD_NONBLOCK | libc::TFD_CLOEXEC);
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        // the reactor holds its own reference, which keeps the state alive for as long
+        // as the timer is registered.
+        if let Err(err) = reactor().register(fd, state.clone()) {
+            libc::close(fd);
+            return Err(err);
+        }
+
+        Ok(NativeTimer { fd, active: false })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn raw(&self) -> RawTimer {
+        self.fd
+    }
+
+    /// Posix timers have no notion of leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(to_timespec(delay), None);
+    }
+
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(to_timespec(start), Some(to_timespec(interval)));
+    }
+
+    fn init(&mut self, mut start: timespec, repeat: Option<timespec>) {
+        dbg_println!("created timer!");
+        self.active = true;
+
+        // a zero `it_value` disarms the timer instead of firing it, so fire as soon as
+        // possible instead.
+        if start.tv_sec == 0 && start.tv_nsec == 0 {
+            start.tv_nsec = 1;
+        }
+
+        let repeat = repeat.unwrap_or(timespec {
+            tv_sec: 0,
+            tv_nsec: 0,
+        });
+
+        let new_value = itimerspec {
+            it_interval: repeat,
+            it_value: start,
+        };
+
+        reactor().set(self.fd, &new_value);
+    }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+
+        // a zeroed `it_value` stops the timer, and throws away any expirations the
+        // reactor hasn't read yet.
+        let stop: itimerspec = unsafe { mem::zeroed() };
+        reactor().set(self.fd, &stop);
+    }
+}
+
+/// The resolution of the monotonic clock timers are measured against - how finely the
+/// kernel can place their expirations.
+pub(crate) fn resolution() -> Duration {
+    let mut res: timespec = unsafe { mem::zeroed() };
+    if unsafe { libc::clock_getres(CLOCK_MONOTONIC, &mut res) } != 0 {
+        // only fails for clocks the kernel doesn't have.
+        return Duration::from_nanos(1);
+    }
+
+    Duration::new(res.tv_sec as u64, res.tv_nsec as u32)
+}
+
+fn clock_id(clock: Clock) -> clockid_t {
+    match clock {
+        Clock::Monotonic => CLOCK_MONOTONIC,
+        Clock::Realtime => CLOCK_REALTIME,
+    }
+}
+
+fn to_timespec(duration: Duration) -> timespec {
+    timespec {
+        // saturate rather than wrapping around to a negative time.
+        tv_sec: duration.as_secs().min(time_t::max_value() as u64) as time_t,
+        tv_nsec: duration.subsec_nanos() as c_long,
+    }
+}
+
+impl Drop for NativeTimer {
+    fn drop(&mut self) {
+        reactor().deregister(self.fd);
+
+        unsafe {
+            libc::close(self.fd);
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use super::{Clock, QosClass, TimerState};
+use std::cell::Cell;
+use std::fmt;
+use std::io;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::Duration;
+
+use js_sys::Function;
+use wasm_bindgen::closure::Closure;
+use wasm_bindgen::JsCast;
+use web_sys::Window;
+
+// in the browser every timer is a `setTimeout` or `setInterval` on the global window,
+// whose callback runs on the event loop like any other js callback.
+
+/// The os timer behind a delay or interval - the id `setTimeout` or `setInterval`
+/// returned, or 0 while the timer isn't armed.
+pub type RawTimer = i32;
+
+/// Which kind of js timer is currently pending, so the right one gets cleared.
+#[derive(Copy, Clone, Debug)]
+enum Scheduled {
+    Idle,
+    Timeout(i32),
+    Interval(i32),
+}
+
+pub struct NativeTimer {
+    window: Window,
+    state: *mut TimerState,
+    // shared with the callback that starts an interval whose first tick isn't one
+    // period away, which swaps the `setTimeout` for a `setInterval`.
+    scheduled: Rc<Cell<Scheduled>>,
+    // js only holds a reference to the callbacks, so they have to live for as long as
+    // the timer can still fire.
+    tick: Closure<dyn FnMut()>,
+    start: Option<Closure<dyn FnMut()>>,
+    active: bool,
+}
+
+// leave out the pointer to the timer state, and the js objects.
+impl fmt::Debug for NativeTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeTimer")
+            .field("scheduled", &self.scheduled.get())
+            .field("active", &self.active)
+            .finish()
+    }
+}
+
+// wasm32-unknown-unknown is single threaded, so the callbacks can never be touched
+// from another thread.
+unsafe impl Send for NativeTimer {}
+unsafe impl Sync for NativeTimer {}
+
+impl NativeTimer {
+    /// The browser only has the one timer clock, so `clock` is ignored.
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, _clock: Clock) -> io::Result<Self> {
+        let window = web_sys::window().ok_or_else(|| {
+            io::Error::new(io::ErrorKind::Other, "no global `window` to set timers on")
+        })?;
+
+        let state = state.as_ptr();
+        let tick = Closure::wrap(Box::new(move || (*state).fire()) as Box<dyn FnMut()>);
+
+        Ok(NativeTimer {
+            window,
+            state,
+            scheduled: Rc::new(Cell::new(Scheduled::Idle)),
+            tick,
+            start: None,
+            active: false,
+        })
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.active
+    }
+
+    pub fn raw(&self) -> RawTimer {
+        match self.scheduled.get() {
+            Scheduled::Idle => 0,
+            Scheduled::Timeout(handle) | Scheduled::Interval(handle) => handle,
+        }
+    }
+
+    /// Browser timers have no notion of leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.clear();
+        self.active = true;
+
+        let handle = set_timeout(&self.window, self.tick.as_ref().unchecked_ref(), delay);
+        self.scheduled.set(Scheduled::Timeout(handle));
+    }
+
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.clear();
+        self.active = true;
+
+        let tick: &Function = self.tick.as_ref().unchecked_ref();
+
+        // `setInterval` first fires one period after it is set, so a different first
+        // tick needs a `setTimeout` that starts the interval once it fires.
+        if start == interval {
+            let handle = set_interval(&self.window, tick, interval);
+            self.scheduled.set(Scheduled::Interval(handle));
+            return;
+        }
+
+        let state = self.state;
+        let window = self.window.clone();
+        let scheduled = self.scheduled.clone();
+        let tick = tick.clone();
+
+        let first = Closure::wrap(Box::new(move || {
+            let handle = set_interval(&window, &tick, interval);
+            scheduled.set(Scheduled::Interval(handle));
+
+            unsafe { (*state).fire() };
+        }) as Box<dyn FnMut()>);
+
+        let handle = set_timeout(&self.window, first.as_ref().unchecked_ref(), start);
+        self.scheduled.set(Scheduled::Timeout(handle));
+        self.start = Some(first);
+    }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+        self.clear();
+    }
+
+    fn clear(&mut self) {
+        match self.scheduled.replace(Scheduled::Idle) {
+            Scheduled::Idle => {}
+            Scheduled::Timeout(handle) => self.window.clear_timeout_with_handle(handle),
+            Scheduled::Interval(handle) => self.window.clear_interval_with_handle(handle),
+        }
+    }
+}
+
+fn set_timeout(window: &Window, callback: &Function, delay: Duration) -> i32 {
+    window
+        .set_timeout_with_callback_and_timeout_and_arguments_0(callback, to_millis(delay))
+        .expect("error starting timer!")
+}
+
+fn set_interval(window: &Window, callback: &Function, interval: Duration) -> i32 {
+    window
+        .set_interval_with_callback_and_timeout_and_arguments_0(callback, to_millis(interval))
+        .expect("error starting timer!")
+}
+
+/// The unit browser timers take. Browsers may still hold them back further - nested
+/// timeouts are clamped to 4ms, and timers in background tabs to a second or more.
+pub(crate) fn resolution() -> Duration {
+    Duration::from_millis(1)
+}
+
+/// Converts a duration into the milliseconds browser timers take, rounding up so
+/// delays never fire early.
+///
+/// Browsers fire anything longer than `i32::max_value()` milliseconds (~24.8 days)
+/// straight away, so longer durations are clamped to that instead.
+fn to_millis(duration: Duration) -> i32 {
+    let millis = (duration.as_nanos() + 999_999) / 1_000_000;
+    millis.min(i32::max_value() as u128) as i32
+}
+
+impl Drop for NativeTimer {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
@@ -1,8 +1,13 @@
-use super::{dbg_println, TimerState};
+use super::{dbg_println, Clock, QosClass, TimerState};
+use std::fmt;
+use std::io;
 use std::ptr;
+use std::sync::{Arc, Once};
 use std::time::Duration;
 
 use winapi::shared::minwindef::{FILETIME, TRUE};
+use winapi::um::errhandlingapi::GetLastError;
+use winapi::um::sysinfoapi::GetSystemTimeAdjustment;
 use winapi::um::winnt::{PTP_CALLBACK_INSTANCE, PTP_TIMER, PVOID};
 
 use winapi::um::threadpoolapiset::{
@@ -12,69 +17,194 @@ use winapi::um::threadpoolapiset::{
     WaitForThreadpoolTimerCallbacks,
 };
 
+/// The os timer behind a delay or interval - a threadpool timer.
+pub type RawTimer = PTP_TIMER;
+
 unsafe extern "system" fn timer_callback(_: PTP_CALLBACK_INSTANCE, context: PVOID, _: PTP_TIMER) {
     let state = context as *mut TimerState;
 
-    (*state).set_done(true);
-    (*state).wake.wake();
+    // re-arming without disarming first doesn't wait for a callback that is already
+    // running, so this may be an expiry of the old arming - the state drops it unless
+    // the new one can have expired by now, allowing for the timer firing up to a
+    // clock tick early.
+    (*state).fire_expired(resolution());
 }
 
-#[derive(Debug)]
 pub struct NativeTimer {
     inner: PTP_TIMER,
     active: bool,
+    leeway: u32,
+}
+
+// the os handle is just a pointer, so leave it out.
+impl fmt::Debug for NativeTimer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("NativeTimer")
+            .field("active", &self.active)
+            .field("leeway", &self.leeway)
+            .finish()
+    }
 }
 
 impl NativeTimer {
-    pub(crate) unsafe fn new(state: *mut TimerState) -> Self {
-        let timer = CreateThreadpoolTimer(Some(timer_callback), state as *mut _, ptr::null_mut());
+    pub(crate) unsafe fn new(state: &Arc<TimerState>, clock: Clock) -> io::Result<Self> {
+        if clock == Clock::Realtime {
+            warn_realtime();
+        }
 
-        NativeTimer {
+        let timer = CreateThreadpoolTimer(
+            Some(timer_callback),
+            state.as_ptr() as *mut _,
+            ptr::null_mut(),
+        );
+        let timer = check_created(timer)?;
+
+        Ok(NativeTimer {
             inner: timer,
             active: false,
-        }
+            leeway: 0,
+        })
     }
 
     pub fn is_active(&self) -> bool {
         self.active
     }
 
-    pub fn init_delay(&mut self, delay: Duration) {
-        let mut ticks = (delay.subsec_nanos() / 100) as i64;
-        ticks += (delay.as_secs() * 10_000_000) as i64;
-        let ticks = -ticks;
+    pub fn raw(&self) -> RawTimer {
+        self.inner
+    }
 
-        self.init(ticks, 0);
+    /// How late the system is allowed to fire the timer, so it can coalesce wakeups.
+    pub fn set_leeway(&mut self, leeway: Duration) {
+        // in whole milliseconds, saturating rather than wrapping around to a short one.
+        self.leeway = leeway.as_millis().min(u32::max_value() as u128) as u32;
+    }
+
+    /// Only macOS schedules timer callbacks by QoS class, so this does nothing.
+    pub fn set_qos_class(&mut self, _qos: QosClass) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(to_due_time(delay), 0);
     }
 
     pub fn init_interval(&mut self, interval: Duration) {
-        let mut ticks = (interval.subsec_nanos() / 100) as i64;
-        ticks += (interval.as_secs() * 10_000_000) as i64;
-        let millis = (ticks / 10_000) as u32;
-        let ticks = -ticks;
+        self.init_interval_at(interval, interval);
+    }
 
-        self.init(ticks, millis);
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(to_due_time(start), to_period(interval));
     }
 
     fn init(&mut self, start: i64, repeat: u32) {
+        // anything else would be an absolute time.
+        debug_assert!(start < 0, "due time {} isn't relative!", start);
+
         self.active = true;
         dbg_println!("timer started!");
 
         unsafe {
-            // i need to find a better way to do this. :/
-            // probably byteorder? windows apis are super weird - where else would a i64
-            // have to be represented as two u32s
-            let mut time: FILETIME = std::mem::transmute(start);
-            SetThreadpoolTimerEx(self.inner, &mut time, repeat, 0);
+            let mut time = to_filetime(start);
+            SetThreadpoolTimerEx(self.inner, &mut time, repeat, self.leeway);
         }
     }
+
+    pub fn disarm(&mut self) {
+        self.active = false;
+
+        unsafe {
+            // cancel the timer, and any callbacks that are queued but haven't started yet.
+            SetThreadpoolTimerEx(self.inner, ptr::null_mut(), 0, 0);
+            WaitForThreadpoolTimerCallbacks(self.inner, TRUE);
+        }
+    }
+}
+
+/// `CreateThreadpoolTimer` returns null when it runs out of resources, and a null
+/// timer must never reach the other threadpool apis - so turn it into the error it
+/// left behind instead.
+unsafe fn check_created(timer: PTP_TIMER) -> io::Result<PTP_TIMER> {
+    if timer.is_null() {
+        return Err(io::Error::from_raw_os_error(GetLastError() as i32));
+    }
+
+    Ok(timer)
+}
+
+/// Threadpool timers only take relative delays against the monotonic clock, so warn
+/// (once) when asked for anything else.
+fn warn_realtime() {
+    static WARNED: Once = Once::new();
+
+    WARNED.call_once(|| {
+        eprintln!(
+            "warning: futures-native-timers can't use the realtime clock on windows, \
+             falling back to the monotonic clock"
+        );
+    });
+}
+
+/// How often the system clock ticks, which is as finely as threadpool timers fire -
+/// usually every 15.6ms, and never less than a millisecond, the unit periods are given
+/// in.
+pub(crate) fn resolution() -> Duration {
+    let (mut adjustment, mut increment, mut disabled) = (0, 0, 0);
+    let res = unsafe { GetSystemTimeAdjustment(&mut adjustment, &mut increment, &mut disabled) };
+
+    let increment = if res == 0 {
+        Duration::from_millis(1)
+    } else {
+        // in 100ns ticks.
+        Duration::from_nanos(u64::from(increment) * 100)
+    };
+
+    increment.max(Duration::from_millis(1))
+}
+
+/// Converts a duration into the 100ns ticks used by the threadpool timer apis,
+/// rounding up so that timers never fire early.
+///
+/// This saturates at `i64::max_value()` (~29,000 years), since anything larger would
+/// wrap around to a positive - absolute - time once negated.
+fn to_ticks(duration: Duration) -> i64 {
+    let ticks = (duration.as_nanos() + 99) / 100;
+    ticks.min(i64::max_value() as u128) as i64
+}
+
+/// Converts a delay into the due time `SetThreadpoolTimerEx` takes - a negative tick
+/// count, meaning relative to now.
+///
+/// A zero due time would be the absolute time 1601-01-01 instead, so a zero delay is
+/// sent as a single tick - fire as soon as possible.
+fn to_due_time(delay: Duration) -> i64 {
+    -to_ticks(delay).max(1)
+}
+
+/// Converts an interval period into the milliseconds `SetThreadpoolTimerEx` takes.
+///
+/// Threadpool timers can't repeat any faster than once a millisecond, so the period
+/// is rounded to the nearest millisecond, and anything shorter is raised to 1ms -
+/// a period of 0 would turn the interval into a one-shot timer.
+fn to_period(interval: Duration) -> u32 {
+    let millis = (interval.as_nanos() + 500_000) / 1_000_000;
+    millis.max(1).min(u32::max_value() as u128) as u32
+}
+
+/// Splits a (signed) 100ns tick count into the two halves of a `FILETIME`.
+///
+/// windows apis are super weird - where else would a i64 have to be represented as
+/// two u32s.
+fn to_filetime(ticks: i64) -> FILETIME {
+    FILETIME {
+        dwLowDateTime: (ticks & 0xFFFF_FFFF) as u32,
+        dwHighDateTime: (ticks >> 32) as u32,
+    }
 }
 
 impl Drop for NativeTimer {
     fn drop(&mut self) {
+        self.disarm();
+
         unsafe {
-            SetThreadpoolTimerEx(self.inner, ptr::null_mut(), 0, 0);
-            WaitForThreadpoolTimerCallbacks(self.inner, TRUE);
             CloseThreadpoolTimer(self.inner);
         }
 
@@ -84,3 +214,105 @@ impl Drop for NativeTimer {
 
 unsafe impl Send for NativeTimer {}
 unsafe impl Sync for NativeTimer {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn from_filetime(time: FILETIME) -> i64 {
+        (((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64) as i64
+    }
+
+    #[test]
+    fn null_timer_is_an_error() {
+        use winapi::um::errhandlingapi::SetLastError;
+
+        const ERROR_NOT_ENOUGH_MEMORY: u32 = 8;
+
+        let err = unsafe {
+            SetLastError(ERROR_NOT_ENOUGH_MEMORY);
+            check_created(ptr::null_mut()).unwrap_err()
+        };
+
+        assert_eq!(err.raw_os_error(), Some(ERROR_NOT_ENOUGH_MEMORY as i32));
+    }
+
+    #[test]
+    fn filetime_round_trip() {
+        let durations = [
+            Duration::from_nanos(100),
+            Duration::from_millis(1),
+            Duration::from_millis(99),
+            Duration::from_secs(1),
+            Duration::new(3, 250_000_000),
+            Duration::from_secs(60 * 60 * 24 * 365),
+        ];
+
+        for &duration in durations.iter() {
+            let ticks = to_due_time(duration);
+            let time = to_filetime(ticks);
+
+            assert_eq!(from_filetime(time), ticks);
+            assert_eq!(-from_filetime(time) as u128, duration.as_nanos() / 100);
+        }
+    }
+
+    #[test]
+    fn due_times() {
+        // zero, and anything under a tick, still waits a tick rather than being taken
+        // as an absolute time.
+        assert_eq!(to_due_time(Duration::from_secs(0)), -1);
+        assert_eq!(to_due_time(Duration::from_nanos(1)), -1);
+        assert_eq!(to_due_time(Duration::from_nanos(100)), -1);
+        assert_eq!(to_due_time(Duration::from_nanos(101)), -2);
+        assert_eq!(to_due_time(Duration::from_millis(250)), -2_500_000);
+
+        assert_eq!(
+            to_due_time(Duration::new(u64::max_value(), 999_999_999)),
+            -i64::max_value()
+        );
+    }
+
+    #[test]
+    fn period_rounding() {
+        assert_eq!(to_period(Duration::from_micros(99_500)), 100);
+        assert_eq!(to_period(Duration::from_micros(99_499)), 99);
+        assert_eq!(to_period(Duration::from_millis(250)), 250);
+        assert_eq!(to_period(Duration::from_micros(500)), 1);
+        assert_eq!(to_period(Duration::from_nanos(1)), 1);
+        assert_eq!(
+            to_period(Duration::from_secs(u64::max_value())),
+            u32::max_value()
+        );
+    }
+
+    #[test]
+    fn huge_durations() {
+        let max_secs = (i64::max_value() / 10_000_000) as u64;
+        let durations = [
+            Duration::new(max_secs, 0),
+            Duration::new(max_secs, 999_999_999),
+            Duration::new(max_secs + 1, 0),
+            Duration::from_secs(u64::max_value()),
+            Duration::new(u64::max_value(), 999_999_999),
+        ];
+
+        for &duration in durations.iter() {
+            let ticks = to_ticks(duration);
+            assert!(ticks > 0);
+
+            // a negative filetime is relative, so the timer stays pending instead of
+            // firing at some absolute time.
+            assert!(from_filetime(to_filetime(-ticks)) < 0);
+        }
+
+        assert_eq!(
+            to_ticks(Duration::new(max_secs, 0)),
+            max_secs as i64 * 10_000_000
+        );
+        assert_eq!(
+            to_ticks(Duration::from_secs(u64::max_value())),
+            i64::max_value()
+        );
+    }
+}
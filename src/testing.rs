@@ -0,0 +1,156 @@
+//! Helpers for testing code that uses timers, without waiting on the real clock.
+
+use std::time::{Duration, Instant};
+
+use super::{saturating_add, Delay, Interval, MockTimer, TimerSource};
+
+/// A clock that only moves when it is advanced, for testing timeout logic
+/// deterministically.
+///
+/// Delays and intervals created from the clock fire as soon as it is advanced past
+/// their deadline. The clock is cheap to clone and can be advanced from any thread,
+/// so it also works with tests driven by a `ThreadPool`.
+#[derive(Clone, Debug)]
+pub struct TestClock {
+    timers: MockTimer,
+    start: Instant,
+}
+
+impl TestClock {
+    /// Creates a clock that starts at the current time.
+    pub fn new() -> Self {
+        TestClock {
+            timers: MockTimer::new(),
+            start: Instant::now(),
+        }
+    }
+
+    /// Returns the clock's current time.
+    pub fn now(&self) -> Instant {
+        saturating_add(self.start, self.timers.elapsed())
+    }
+
+    /// Moves the clock forward by `by`, firing everything that comes due.
+    pub fn advance(&self, by: Duration) {
+        self.timers.advance(by);
+    }
+
+    /// Moves the clock forward to `to`, firing everything that comes due. Times that
+    /// have already passed leave the clock where it is.
+    pub fn advance_to(&self, to: Instant) {
+        let now = self.now();
+        if to > now {
+            self.advance(to - now);
+        }
+    }
+
+    /// Returns when each armed delay or interval on this clock is next due, soonest
+    /// first.
+    ///
+    /// Delays and intervals are only armed once they are first polled.
+    pub fn pending(&self) -> Vec<Instant> {
+        self.timers
+            .clock()
+            .deadlines()
+            .into_iter()
+            .map(|deadline| saturating_add(self.start, deadline))
+            .collect()
+    }
+
+    /// Returns a delay that fires once the clock has moved `delay` past the time it is
+    /// first polled.
+    pub fn delay(&self, delay: Duration) -> Delay {
+        self.timers.delay(delay)
+    }
+
+    /// Returns an interval that ticks every `period` of clock time.
+    pub fn interval(&self, period: Duration) -> Interval {
+        self.timers.interval(period)
+    }
+}
+
+impl Default for TestClock {
+    fn default() -> Self {
+        TestClock::new()
+    }
+}
+
+impl TimerSource for TestClock {
+    type Delay = Delay;
+    type Interval = Interval;
+
+    fn delay(&self, delay: Duration) -> Delay {
+        self.timers.delay(delay)
+    }
+
+    fn interval(&self, period: Duration) -> Interval {
+        self.timers.interval(period)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+
+    #[test]
+    fn pending_on_test_clock() {
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let clock = TestClock::new();
+        let mut timeout = clock.delay(Duration::from_secs(1));
+        let mut stream = clock.interval(Duration::from_millis(99));
+
+        assert!(Pin::new(&mut timeout).poll(noop_waker_ref()).is_pending());
+        assert!(Pin::new(&mut stream)
+            .poll_next(noop_waker_ref())
+            .is_pending());
+        assert_eq!(
+            clock.pending(),
+            vec![
+                clock.now() + Duration::from_millis(99),
+                clock.now() + Duration::from_secs(1),
+            ]
+        );
+
+        clock.advance(Duration::from_millis(100));
+        assert!(Pin::new(&mut stream).poll_next(noop_waker_ref()).is_ready());
+        assert_eq!(
+            clock.pending(),
+            vec![
+                clock.now() + Duration::from_millis(98),
+                clock.now() + Duration::from_millis(900),
+            ]
+        );
+
+        // too far out to be an `Instant`.
+        let mut never = clock.delay(Duration::from_secs(u64::max_value() / 2));
+        assert!(Pin::new(&mut never).poll(noop_waker_ref()).is_pending());
+        assert!(clock.pending()[2] > clock.now() + Duration::from_secs(60 * 60 * 24 * 365));
+    }
+
+    #[test]
+    fn advance_test_clock_from_another_thread() {
+        let clock = TestClock::new();
+        let delay = clock.delay(Duration::from_secs(60 * 60));
+
+        let remote = clock.clone();
+        let driver = std::thread::spawn(move || {
+            while remote.pending().is_empty() {
+                std::thread::yield_now();
+            }
+
+            let deadline = remote.pending()[0];
+            remote.advance_to(deadline);
+        });
+
+        let start = Instant::now();
+        block_on(delay);
+        driver.join().unwrap();
+
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(clock.pending().is_empty());
+    }
+}
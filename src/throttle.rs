@@ -0,0 +1,182 @@
+use std::fmt;
+use std::pin::Pin;
+use std::time::Duration;
+
+use futures::prelude::*;
+use futures::stream::FusedStream;
+use futures::task::{Poll, Waker};
+use pin_utils::{unsafe_pinned, unsafe_unpinned};
+
+use super::Delay;
+
+/// A stream that yields the items of another at most once every `period`.
+///
+/// Created by the `throttle` method on this crate's `StreamExt`. An item that arrives
+/// while the gate is closed is held back until the gate opens, and replaces any item
+/// that was already waiting - so after a burst, only the last item of it comes
+/// through.
+#[must_use = "streams do nothing unless polled"]
+pub struct Throttle<S: Stream> {
+    stream: S,
+    // armed whenever an item is yielded, and lets the next one through once it fires.
+    delay: Delay,
+    period: Duration,
+    pending: Option<S::Item>,
+    open: bool,
+    done: bool,
+}
+
+impl<S: Stream> Throttle<S> {
+    unsafe_pinned!(stream: S);
+
+    unsafe_pinned!(delay: Delay);
+
+    unsafe_unpinned!(pending: Option<S::Item>);
+
+    unsafe_unpinned!(open: bool);
+
+    unsafe_unpinned!(done: bool);
+
+    pub(crate) fn new(stream: S, period: Duration) -> Self {
+        Throttle {
+            stream,
+            delay: Delay::new(period),
+            period,
+            pending: None,
+            open: true,
+            done: false,
+        }
+    }
+
+    /// Returns the wrapped stream, dropping any item that was held back.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+
+    /// Yields `item`, and closes the gate for the next period.
+    fn emit(mut self: Pin<&mut Self>, item: S::Item) -> Poll<Option<S::Item>> {
+        let period = self.period;
+        self.as_mut().delay().reset(period);
+
+        *self.open() = false;
+        Poll::Ready(Some(item))
+    }
+}
+
+impl<S: Stream + Unpin> Unpin for Throttle<S> {}
+
+impl<S: Stream + fmt::Debug> fmt::Debug for Throttle<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Throttle")
+            .field("stream", &self.stream)
+            .field("period", &self.period)
+            .field("pending", &self.pending.is_some())
+            .finish()
+    }
+}
+
+impl<S: Stream> Stream for Throttle<S> {
+    type Item = S::Item;
+
+    fn poll_next(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Option<Self::Item>> {
+        // take everything the stream has ready, keeping only the latest item.
+        while !self.done {
+            match self.as_mut().stream().poll_next(w) {
+                Poll::Ready(Some(item)) => {
+                    if self.open {
+                        return self.emit(item);
+                    }
+
+                    *self.as_mut().pending() = Some(item);
+                }
+                Poll::Ready(None) => *self.as_mut().done() = true,
+                Poll::Pending => break,
+            }
+        }
+
+        if !self.open {
+            if self.done && self.pending.is_none() {
+                return Poll::Ready(None);
+            }
+
+            if let Poll::Pending = self.as_mut().delay().poll(w) {
+                return Poll::Pending;
+            }
+
+            match self.as_mut().pending().take() {
+                Some(item) => return self.emit(item),
+                None => *self.as_mut().open() = true,
+            }
+        }
+
+        // the gate is open, so nothing is held back.
+        if self.done {
+            Poll::Ready(None)
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S: Stream> FusedStream for Throttle<S> {
+    fn is_terminated(&self) -> bool {
+        self.done && self.pending.is_none()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::StreamExt as _;
+    use futures::executor::block_on;
+    use futures::stream;
+    use std::time::Instant;
+
+    #[test]
+    fn throttle_burst() {
+        let start = Instant::now();
+        let items: Vec<_> = block_on(
+            stream::iter(0..10)
+                .throttle(Duration::from_millis(50))
+                .collect(),
+        );
+
+        // the first item goes straight through, and the last comes a period later.
+        assert_eq!(items, vec![0, 9]);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+    }
+
+    #[test]
+    fn throttle_spacing() {
+        use futures::channel::mpsc;
+        use std::thread;
+
+        let (send, recv) = mpsc::unbounded();
+        thread::spawn(move || {
+            for burst in &[0..5, 5..10] {
+                for i in burst.clone() {
+                    send.unbounded_send(i).unwrap();
+                }
+                thread::sleep(Duration::from_millis(200));
+            }
+        });
+
+        let mut throttled = recv.throttle(Duration::from_millis(50));
+        let work = async {
+            let mut items = vec![];
+            while let Some(i) = await!(throttled.next()) {
+                items.push((i, Instant::now()));
+            }
+
+            items
+        };
+
+        let items = block_on(work);
+        let values: Vec<_> = items.iter().map(|&(i, _)| i).collect();
+        assert_eq!(values, vec![0, 4, 5, 9]);
+
+        for pair in items.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= Duration::from_millis(45));
+        }
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::{Duration, Instant};
+
+/// A shared starting point for timers that should keep exact time relative to each
+/// other.
+///
+/// Timers built with [`Delay::at_base`](crate::Delay::at_base) fire at an offset from
+/// the same captured instant, so the gaps between them are exactly the gaps between
+/// their offsets - however far apart they were created, or first polled.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct TimeBase {
+    start: Instant,
+}
+
+impl TimeBase {
+    /// Captures the current instant as the base.
+    pub fn now() -> Self {
+        TimeBase::from_instant(Instant::now())
+    }
+
+    /// Uses `start` as the base.
+    pub fn from_instant(start: Instant) -> Self {
+        TimeBase { start }
+    }
+
+    /// Returns the instant the base was captured at.
+    pub fn instant(&self) -> Instant {
+        self.start
+    }
+
+    /// Returns the instant `offset` after the base, or the latest instant there is if
+    /// that is too far out.
+    pub fn at(&self, offset: Duration) -> Instant {
+        crate::saturating_add(self.start, offset)
+    }
+
+    /// Returns how long ago the base was captured.
+    pub fn elapsed(&self) -> Duration {
+        self.start.elapsed()
+    }
+}
+
+impl From<Instant> for TimeBase {
+    fn from(start: Instant) -> Self {
+        TimeBase::from_instant(start)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Delay;
+    use futures::executor::block_on;
+
+    #[test]
+    fn delay_at_base() {
+        use std::thread;
+
+        let base = TimeBase::now();
+        let first = Delay::at_base(&base, Duration::from_millis(40));
+        thread::sleep(Duration::from_millis(20));
+        let second = Delay::at_base(&base, Duration::from_millis(60));
+
+        // both deadlines count from the base, not from when each delay was created.
+        assert_eq!(first.deadline(), base.at(Duration::from_millis(40)));
+        assert_eq!(second.deadline(), base.at(Duration::from_millis(60)));
+
+        block_on(first);
+        assert!(base.elapsed() >= Duration::from_millis(40));
+        block_on(second);
+        let elapsed = base.elapsed();
+        assert!(elapsed >= Duration::from_millis(60));
+        assert!(elapsed < Duration::from_millis(500));
+    }
+
+    #[test]
+    fn huge_offset() {
+        use futures::prelude::*;
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let base = TimeBase::now();
+        let far = base.at(Duration::from_secs(u64::max_value()));
+        assert!(far > base.at(Duration::from_secs(60 * 60 * 24 * 365)));
+
+        let mut delay = Delay::at_base(&base, Duration::from_secs(u64::max_value()));
+        assert_eq!(delay.deadline(), far);
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+    }
+}
@@ -1,10 +1,16 @@
-use crate::Delay;
+use crate::{Debounce, Delay, Throttle};
 use futures::{
+    future::Either,
     prelude::*,
+    stream::FusedStream,
     task::{Poll, Waker},
 };
-use pin_utils::unsafe_pinned;
-use std::{error, fmt, pin::Pin, time::Duration};
+use pin_utils::{unsafe_pinned, unsafe_unpinned};
+use std::{
+    error, fmt,
+    pin::Pin,
+    time::{Duration, Instant},
+};
 
 pub trait FutureExt {
     fn timeout(self, timeout: Duration) -> Timeout<Self>
@@ -17,10 +23,121 @@ pub trait FutureExt {
             delay,
         }
     }
+
+    /// Like `timeout`, but the timeout fires at `deadline` instead of after a duration.
+    ///
+    /// This is useful for making a group of futures respect the same deadline.
+    fn timeout_at(self, deadline: Instant) -> Timeout<Self>
+    where
+        Self: Sized,
+    {
+        let delay = Delay::at(deadline);
+        Timeout {
+            future: self,
+            delay,
+        }
+    }
+
+    /// Like `timeout`, but calls `f` to build the error when the timeout fires first.
+    ///
+    /// `f` is only called once, and only if the future times out.
+    fn timeout_map<E, G>(self, timeout: Duration, f: G) -> TimeoutMap<Self, G>
+    where
+        Self: Sized,
+        G: FnOnce() -> E,
+    {
+        TimeoutMap {
+            timeout: self.timeout(timeout),
+            f: Some(f),
+        }
+    }
+
+    /// Like `timeout`, but hands back the still running delay along with the output,
+    /// so whatever is left of the time budget can be spent on the next operation.
+    fn timeout_with_remaining(self, timeout: Duration) -> TimeoutWithRemaining<Self>
+    where
+        Self: Sized,
+    {
+        TimeoutWithRemaining {
+            future: self,
+            delay: Some(Delay::new(timeout)),
+        }
+    }
+
+    /// Like `timeout`, but yields `Either::Right(())` when the timeout fires first,
+    /// instead of an error - which keeps a timeout apart from the future's own errors
+    /// when it already returns a `Result`.
+    fn or_timeout(self, timeout: Duration) -> OrTimeout<Self>
+    where
+        Self: Sized,
+    {
+        OrTimeout {
+            future: self,
+            delay: Delay::new(timeout),
+        }
+    }
+
+    /// Makes the future take at least `duration`, counted from its first poll - its
+    /// output is held back until the delay has fired as well.
+    ///
+    /// Unlike `timeout` this always waits for the future to finish, however long it
+    /// takes.
+    fn at_least(self, duration: Duration) -> AtLeast<Self>
+    where
+        Self: Future + Sized,
+    {
+        AtLeast {
+            future: self,
+            delay: Delay::new(duration),
+            output: None,
+        }
+    }
 }
 
 impl<F, T> FutureExt for F where F: Future<Output = T> {}
 
+pub trait StreamExt {
+    /// Errors if any single item of the stream takes longer than `per_item` to arrive.
+    ///
+    /// The timer restarts after every item, and after every timeout, so the stream
+    /// keeps going after yielding an error.
+    fn timeout(self, per_item: Duration) -> TimeoutStream<Self>
+    where
+        Self: Sized,
+    {
+        TimeoutStream {
+            stream: self,
+            delay: Delay::new(per_item),
+            timeout: per_item,
+        }
+    }
+
+    /// Yields the items of the stream at most once every `period`.
+    ///
+    /// The first item goes straight through. Items that arrive less than `period`
+    /// after the last one yielded are held back until the period is up, and only the
+    /// latest of them is kept, so a burst shrinks to its first and last item.
+    fn throttle(self, period: Duration) -> Throttle<Self>
+    where
+        Self: Stream + Sized,
+    {
+        Throttle::new(self, period)
+    }
+
+    /// Yields an item of the stream only once `period` has passed without a newer
+    /// one arriving - so out of each burst, only the last item comes through.
+    ///
+    /// When the stream ends, the item that was still waiting is yielded straight away.
+    fn debounce(self, period: Duration) -> Debounce<Self>
+    where
+        Self: Stream + Sized,
+    {
+        Debounce::new(self, period)
+    }
+}
+
+impl<S, T> StreamExt for S where S: Stream<Item = T> {}
+
 #[derive(Debug)]
 #[must_use = "futures do nothing unless polled"]
 pub struct Timeout<F> {
@@ -32,6 +149,42 @@ impl<F> Timeout<F> {
     unsafe_pinned!(future: F);
 
     unsafe_pinned!(delay: Delay);
+
+    /// Returns a reference to the wrapped future.
+    pub fn get_ref(&self) -> &F {
+        &self.future
+    }
+
+    /// Returns a pinned mutable reference to the wrapped future.
+    pub fn get_mut(self: Pin<&mut Self>) -> Pin<&mut F> {
+        self.future()
+    }
+
+    /// Cancels the timeout and returns the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+
+    /// Restarts the timeout so that it fires `timeout` from now, re-arming the
+    /// existing delay - e.g. to keep an idle timeout from firing while there is still
+    /// activity.
+    pub fn reset(self: Pin<&mut Self>, timeout: Duration) {
+        self.delay().reset(timeout);
+    }
+
+    /// Returns when the timeout fires.
+    ///
+    /// The timeout starts counting when it is first polled, so before then this is
+    /// the full timeout from now.
+    pub fn deadline(&self) -> Instant {
+        self.delay.deadline()
+    }
+
+    /// Returns how long the timeout has been counting, since it was first polled or
+    /// last reset - or zero, before the first poll.
+    pub fn elapsed(&self) -> Duration {
+        self.delay.elapsed()
+    }
 }
 
 impl<F: Unpin> Unpin for Timeout<F> {}
@@ -45,7 +198,11 @@ where
     fn poll(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Self::Output> {
         // Check if timed out
         if let Poll::Ready(_) = self.as_mut().delay().poll(w) {
-            Poll::Ready(Err(TimeoutError))
+            let delay = self.as_mut().delay();
+            Poll::Ready(Err(TimeoutError {
+                timeout: delay.duration(),
+                elapsed: delay.elapsed(),
+            }))
         } else {
             // Poll main future
             self.as_mut().future().poll(w).map(Ok)
@@ -53,11 +210,470 @@ where
     }
 }
 
+#[derive(Debug)]
+#[must_use = "streams do nothing unless polled"]
+pub struct TimeoutStream<S> {
+    stream: S,
+    delay: Delay,
+    timeout: Duration,
+}
+
+impl<S> TimeoutStream<S> {
+    unsafe_pinned!(stream: S);
+
+    unsafe_pinned!(delay: Delay);
+
+    /// Returns the wrapped stream.
+    pub fn into_inner(self) -> S {
+        self.stream
+    }
+}
+
+impl<S: Unpin> Unpin for TimeoutStream<S> {}
+
+impl<S, T> Stream for TimeoutStream<S>
+where
+    S: Stream<Item = T>,
+{
+    type Item = Result<T, TimeoutError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Option<Self::Item>> {
+        let timeout = self.timeout;
+
+        match self.as_mut().stream().poll_next(w) {
+            Poll::Ready(Some(item)) => {
+                self.as_mut().delay().reset(timeout);
+                return Poll::Ready(Some(Ok(item)));
+            }
+            Poll::Ready(None) => return Poll::Ready(None),
+            Poll::Pending => {}
+        }
+
+        if let Poll::Ready(_) = self.as_mut().delay().poll(w) {
+            let mut delay = self.as_mut().delay();
+            let err = TimeoutError {
+                timeout,
+                elapsed: delay.elapsed(),
+            };
+
+            delay.reset(timeout);
+            Poll::Ready(Some(Err(err)))
+        } else {
+            Poll::Pending
+        }
+    }
+}
+
+impl<S: FusedStream> FusedStream for TimeoutStream<S> {
+    fn is_terminated(&self) -> bool {
+        self.stream.is_terminated()
+    }
+}
+
+#[must_use = "futures do nothing unless polled"]
+pub struct TimeoutMap<F, G> {
+    timeout: Timeout<F>,
+    f: Option<G>,
+}
+
+impl<F, G> TimeoutMap<F, G> {
+    unsafe_pinned!(timeout: Timeout<F>);
+
+    unsafe_unpinned!(f: Option<G>);
+}
+
+impl<F: Unpin, G> Unpin for TimeoutMap<F, G> {}
+
+impl<F: fmt::Debug, G> fmt::Debug for TimeoutMap<F, G> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TimeoutMap")
+            .field("timeout", &self.timeout)
+            .finish()
+    }
+}
+
+impl<F, G, T, E> Future for TimeoutMap<F, G>
+where
+    F: Future<Output = T>,
+    G: FnOnce() -> E,
+{
+    type Output = Result<T, E>;
+
+    fn poll(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Self::Output> {
+        match self.as_mut().timeout().poll(w) {
+            Poll::Ready(Ok(value)) => Poll::Ready(Ok(value)),
+            Poll::Ready(Err(_)) => {
+                let f = self
+                    .as_mut()
+                    .f()
+                    .take()
+                    .expect("TimeoutMap polled after completion");
+                Poll::Ready(Err(f()))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct TimeoutWithRemaining<F> {
+    future: F,
+    // moved out once the future completes.
+    delay: Option<Delay>,
+}
+
+impl<F> TimeoutWithRemaining<F> {
+    unsafe_pinned!(future: F);
+
+    unsafe_unpinned!(delay: Option<Delay>);
+}
+
+impl<F: Unpin> Unpin for TimeoutWithRemaining<F> {}
+
+impl<F, T> Future for TimeoutWithRemaining<F>
+where
+    F: Future<Output = T>,
+{
+    type Output = Result<(T, Delay), TimeoutError>;
+
+    fn poll(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Self::Output> {
+        let delay = self
+            .as_mut()
+            .delay()
+            .as_mut()
+            .expect("TimeoutWithRemaining polled after completion");
+
+        if let Poll::Ready(_) = Pin::new(&mut *delay).poll(w) {
+            return Poll::Ready(Err(TimeoutError {
+                timeout: delay.duration(),
+                elapsed: delay.elapsed(),
+            }));
+        }
+
+        match self.as_mut().future().poll(w) {
+            Poll::Ready(value) => {
+                let delay = self.as_mut().delay().take().unwrap();
+                Poll::Ready(Ok((value, delay)))
+            }
+            Poll::Pending => Poll::Pending,
+        }
+    }
+}
+
+#[derive(Debug)]
+#[must_use = "futures do nothing unless polled"]
+pub struct OrTimeout<F> {
+    future: F,
+    delay: Delay,
+}
+
+impl<F> OrTimeout<F> {
+    unsafe_pinned!(future: F);
+
+    unsafe_pinned!(delay: Delay);
+
+    /// Cancels the timeout and returns the wrapped future.
+    pub fn into_inner(self) -> F {
+        self.future
+    }
+}
+
+impl<F: Unpin> Unpin for OrTimeout<F> {}
+
+impl<F, T> Future for OrTimeout<F>
+where
+    F: Future<Output = T>,
+{
+    type Output = Either<T, ()>;
+
+    fn poll(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Self::Output> {
+        if let Poll::Ready(()) = self.as_mut().delay().poll(w) {
+            return Poll::Ready(Either::Right(()));
+        }
+
+        self.as_mut().future().poll(w).map(Either::Left)
+    }
+}
+
+#[must_use = "futures do nothing unless polled"]
+pub struct AtLeast<F: Future> {
+    future: F,
+    delay: Delay,
+    // the future's output, once it has finished before the delay.
+    output: Option<F::Output>,
+}
+
+impl<F: Future> AtLeast<F> {
+    unsafe_pinned!(future: F);
+
+    unsafe_pinned!(delay: Delay);
+
+    unsafe_unpinned!(output: Option<F::Output>);
+}
+
+impl<F: Future + Unpin> Unpin for AtLeast<F> {}
+
+impl<F: Future + fmt::Debug> fmt::Debug for AtLeast<F> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("AtLeast")
+            .field("future", &self.future)
+            .field("delay", &self.delay)
+            .field("finished", &self.output.is_some())
+            .finish()
+    }
+}
+
+impl<F: Future> Future for AtLeast<F> {
+    type Output = F::Output;
+
+    fn poll(mut self: Pin<&mut Self>, w: &Waker) -> Poll<Self::Output> {
+        // poll both every time, so that the delay is armed on the first poll whichever
+        // finishes first.
+        if self.output.is_none() {
+            if let Poll::Ready(output) = self.as_mut().future().poll(w) {
+                *self.as_mut().output() = Some(output);
+            }
+        }
+
+        if let Poll::Pending = self.as_mut().delay().poll(w) {
+            return Poll::Pending;
+        }
+
+        match self.as_mut().output().take() {
+            Some(output) => Poll::Ready(output),
+            None => Poll::Pending,
+        }
+    }
+}
+
 #[derive(Copy, Clone, Debug)]
-pub struct TimeoutError;
+pub struct TimeoutError {
+    timeout: Duration,
+    elapsed: Duration,
+}
+
+impl TimeoutError {
+    /// Returns the timeout that was configured.
+    pub fn timeout(&self) -> Duration {
+        self.timeout
+    }
+
+    /// Returns how long the future actually ran for before timing out. This can be
+    /// slightly longer than the configured timeout.
+    pub fn elapsed(&self) -> Duration {
+        self.elapsed
+    }
+}
+
 impl error::Error for TimeoutError {}
 impl fmt::Display for TimeoutError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(f, "future timed out")
+        write!(f, "future timed out after {:?}", self.elapsed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Interval;
+    use futures::executor::block_on;
+    // the `StreamExt` above shadows the one from `futures`.
+    use futures::stream::StreamExt as _;
+    use std::thread;
+
+    #[test]
+    fn timeout() {
+        use futures::future::empty;
+
+        // The empty future will always return Poll::Pending, so this will always timeout first
+        let result: Result<(), TimeoutError> = block_on(empty().timeout(Duration::new(0, 0)));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn timeout_error_durations() {
+        use futures::future::empty;
+
+        let timeout = Duration::from_millis(50);
+        let err = block_on(empty::<()>().timeout(timeout)).unwrap_err();
+
+        assert_eq!(err.timeout(), timeout);
+        assert!(err.elapsed() >= timeout);
+        assert_eq!(
+            err.to_string(),
+            format!("future timed out after {:?}", err.elapsed())
+        );
+    }
+
+    #[test]
+    fn timeout_into_inner() {
+        use futures::future::ready;
+        let timeout = ready(3).timeout(Duration::from_millis(10));
+
+        // the timeout is cancelled along with its delay
+        let future = timeout.into_inner();
+        thread::sleep(Duration::from_millis(30));
+        assert_eq!(block_on(future), 3);
+    }
+
+    #[test]
+    fn timeout_map() {
+        use futures::future::{empty, ready};
+
+        #[derive(Debug, PartialEq)]
+        enum Error {
+            TimedOut,
+        }
+
+        let result: Result<(), Error> =
+            block_on(empty().timeout_map(Duration::from_millis(10), || Error::TimedOut));
+        assert_eq!(result, Err(Error::TimedOut));
+
+        let result = block_on(ready(1).timeout_map(Duration::from_secs(1), || -> Error {
+            unreachable!("the future finished first")
+        }));
+        assert_eq!(result, Ok(1));
+    }
+
+    #[test]
+    fn reset_timeout() {
+        use futures::future::empty;
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let start = Instant::now();
+        let mut timeout = empty::<()>().timeout(Duration::from_millis(50));
+
+        // keep resetting it, well past the original timeout.
+        for _ in 0..6 {
+            assert!(Pin::new(&mut timeout).poll(noop_waker_ref()).is_pending());
+            thread::sleep(Duration::from_millis(25));
+            Pin::new(&mut timeout).reset(Duration::from_millis(50));
+        }
+        assert!(start.elapsed() >= Duration::from_millis(150));
+
+        let err = block_on(timeout).unwrap_err();
+        assert_eq!(err.timeout(), Duration::from_millis(50));
+        assert!(start.elapsed() >= Duration::from_millis(200));
+    }
+
+    #[test]
+    fn timeout_deadline() {
+        use futures::future::empty;
+        use futures::task::noop_waker_ref;
+        use std::pin::Pin;
+
+        let mut timeout = empty::<()>().timeout(Duration::from_millis(50));
+        assert_eq!(timeout.elapsed(), Duration::from_secs(0));
+
+        let start = Instant::now();
+        assert!(Pin::new(&mut timeout).poll(noop_waker_ref()).is_pending());
+        let deadline = timeout.deadline();
+        assert!(deadline >= start + Duration::from_millis(50));
+        assert!(deadline <= Instant::now() + Duration::from_millis(50));
+
+        thread::sleep(Duration::from_millis(20));
+        assert!(timeout.elapsed() >= Duration::from_millis(20));
+        assert_eq!(timeout.deadline(), deadline);
+
+        // a reset starts the count again.
+        Pin::new(&mut timeout).reset(Duration::from_millis(50));
+        assert!(timeout.elapsed() < Duration::from_millis(20));
+        assert!(timeout.deadline() > deadline);
+    }
+
+    #[test]
+    fn at_least() {
+        use futures::future::ready;
+
+        // a future that is ready straight away still takes the whole duration.
+        let start = Instant::now();
+        let res = block_on(ready(7).at_least(Duration::from_millis(50)));
+        assert_eq!(res, 7);
+        assert!(start.elapsed() >= Duration::from_millis(50));
+
+        // and a slow one isn't cut short.
+        let start = Instant::now();
+        let slow = async {
+            await!(Delay::new(Duration::from_millis(80)));
+            "slow"
+        };
+        let res = block_on(slow.at_least(Duration::from_millis(20)));
+        assert_eq!(res, "slow");
+        assert!(start.elapsed() >= Duration::from_millis(80));
+    }
+
+    #[test]
+    fn or_timeout() {
+        use futures::future::{empty, ready, Either};
+
+        // the future's own error comes through as a value, apart from the timeout.
+        let failed = ready(Err::<(), _>("failed")).or_timeout(Duration::from_secs(1));
+        match block_on(failed) {
+            Either::Left(result) => assert_eq!(result, Err("failed")),
+            Either::Right(()) => panic!("the future finished first"),
+        }
+
+        let start = Instant::now();
+        let timed_out = empty::<Result<(), &str>>().or_timeout(Duration::from_millis(20));
+        assert!(match block_on(timed_out) {
+            Either::Left(_) => false,
+            Either::Right(()) => true,
+        });
+        assert!(start.elapsed() >= Duration::from_millis(20));
+    }
+
+    #[test]
+    fn timeout_with_remaining() {
+        use futures::future::empty;
+
+        let budget = Duration::from_millis(200);
+        let start = Instant::now();
+
+        let first = Delay::new(Duration::from_millis(50));
+        let ((), remaining) = block_on(first.timeout_with_remaining(budget)).unwrap();
+        assert!(remaining.remaining() < Duration::from_millis(160));
+
+        // the rest of the budget runs out at the original deadline.
+        let result = block_on(empty::<()>().timeout_with_remaining(remaining.remaining()));
+        let elapsed = start.elapsed();
+        assert!(result.is_err());
+        assert!(elapsed >= Duration::from_millis(190));
+        assert!(elapsed < Duration::from_millis(400));
+
+        block_on(remaining);
+    }
+
+    #[test]
+    fn stream_timeout() {
+        let mut fast = Interval::new(Duration::from_millis(10)).timeout(Duration::from_millis(50));
+        let mut slow = Interval::new(Duration::from_millis(80)).timeout(Duration::from_millis(50));
+
+        let work = async {
+            for _ in 0..3 {
+                assert!(await!(fast.next()).unwrap().is_ok());
+            }
+
+            // the first tick takes too long, but the stream keeps going
+            assert!(await!(slow.next()).unwrap().is_err());
+            assert!(await!(slow.next()).unwrap().is_ok());
+        };
+
+        block_on(work);
+    }
+
+    #[test]
+    fn timeout_at() {
+        use futures::future::{empty, ready};
+
+        let deadline = Instant::now() + Duration::from_millis(50);
+
+        let result: Result<(), TimeoutError> = block_on(empty().timeout_at(deadline));
+        assert!(result.is_err());
+        assert!(Instant::now() >= deadline);
+
+        let result = block_on(ready(5).timeout_at(deadline + Duration::from_secs(1)));
+        assert_eq!(result.unwrap(), 5);
     }
 }
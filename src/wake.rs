@@ -0,0 +1,205 @@
+use std::mem;
+use std::ptr;
+use std::sync::{mpsc, Arc, Mutex, Once};
+use std::thread;
+
+use futures::task::{ArcWake, Waker};
+
+/// Where a timer's task is woken from when the timer fires.
+///
+/// Every backend already fires its timers from a thread of the os's, or of this
+/// crate's - never from a signal handler - so waking straight from there is safe
+/// everywhere. Some executors would still rather not have their wakers called from
+/// threads they don't know about, or want the timer thread kept as short as possible.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum WakeStrategy {
+    /// Wakes the task from the thread the timer fires on.
+    ///
+    /// This is the default.
+    Inline,
+    /// Hands the task's waker to a thread of this crate's when the timer fires, which
+    /// then wakes it.
+    ///
+    /// The timer thread only ever queues the waker, at the cost of a hop to another
+    /// thread before the task is woken.
+    Deferred,
+    /// Queues the task's waker when the timer fires, and leaves it there until
+    /// [`drive_ready_timers`] is called.
+    ///
+    /// This lets a single-threaded executor decide when its wakeups happen, and have
+    /// all of them on its own thread. The catch is that nothing else ever calls
+    /// `drive_ready_timers` - a fired timer's task isn't woken until the executor gets
+    /// round to it, so it has to call it often enough for its timers' precision, and
+    /// can't block indefinitely while any of its timers are running.
+    Manual,
+}
+
+impl Default for WakeStrategy {
+    fn default() -> Self {
+        WakeStrategy::Inline
+    }
+}
+
+/// Wraps `waker`, so that waking it queues it instead - for the wake thread, or with
+/// `manual`, for `drive_ready_timers`.
+pub(crate) fn deferred(waker: &Waker, manual: bool) -> Waker {
+    ArcWake::into_waker(Arc::new(Deferred {
+        waker: waker.clone(),
+        manual,
+    }))
+}
+
+struct Deferred {
+    waker: Waker,
+    manual: bool,
+}
+
+impl ArcWake for Deferred {
+    fn wake(arc_self: &Arc<Self>) {
+        let waker = arc_self.waker.clone();
+
+        if arc_self.manual {
+            ready_queue().lock().unwrap().push(waker);
+        } else {
+            // the wake thread never exits, so there's always someone receiving.
+            let _ = wake_queue().lock().unwrap().send(waker);
+        }
+    }
+}
+
+/// Wakes the tasks of every `WakeStrategy::Manual` timer that has fired since the last
+/// call, returning how many were woken.
+///
+/// The wakers are called on this thread, after they have all been taken off the queue -
+/// so a waker that polls its task straight away can fire more timers without
+/// deadlocking, and those are left for the next call.
+pub fn drive_ready_timers() -> usize {
+    let ready = mem::replace(&mut *ready_queue().lock().unwrap(), Vec::new());
+    let count = ready.len();
+
+    for waker in ready {
+        waker.wake();
+    }
+
+    count
+}
+
+static READY_QUEUE_INIT: Once = Once::new();
+static mut READY_QUEUE: *const Mutex<Vec<Waker>> = ptr::null();
+
+/// Returns the queue `drive_ready_timers` drains.
+fn ready_queue() -> &'static Mutex<Vec<Waker>> {
+    READY_QUEUE_INIT.call_once(|| unsafe {
+        READY_QUEUE = Box::into_raw(Box::new(Mutex::new(Vec::new())));
+    });
+
+    unsafe { &*READY_QUEUE }
+}
+
+static WAKE_QUEUE_INIT: Once = Once::new();
+static mut WAKE_QUEUE: *const Mutex<mpsc::Sender<Waker>> = ptr::null();
+
+/// Returns the queue the wake thread drains, starting the thread the first time.
+fn wake_queue() -> &'static Mutex<mpsc::Sender<Waker>> {
+    WAKE_QUEUE_INIT.call_once(|| {
+        let (tx, rx) = mpsc::channel::<Waker>();
+
+        thread::Builder::new()
+            .name("futures-native-timers-waker".into())
+            .spawn(move || {
+                for waker in rx {
+                    waker.wake();
+                }
+            })
+            .expect("error spawning deferred wake thread!");
+
+        unsafe {
+            WAKE_QUEUE = Box::into_raw(Box::new(Mutex::new(tx)));
+        }
+    });
+
+    unsafe { &*WAKE_QUEUE }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TimerBuilder;
+    use futures::executor::block_on;
+    use futures::prelude::*;
+    use std::sync::atomic::Ordering::{Acquire, Release};
+    use std::time::Duration;
+
+    #[test]
+    fn deferred_wakes() {
+        use futures::task::ArcWake;
+        use std::pin::Pin;
+        use std::sync::{mpsc, Mutex};
+
+        struct Woken(Mutex<mpsc::Sender<Option<String>>>);
+
+        impl ArcWake for Woken {
+            fn wake(arc_self: &Arc<Self>) {
+                let name = thread::current().name().map(String::from);
+                let _ = arc_self.0.lock().unwrap().send(name);
+            }
+        }
+
+        let (tx, rx) = mpsc::channel();
+        let waker = ArcWake::into_waker(Arc::new(Woken(Mutex::new(tx))));
+
+        let mut delay = TimerBuilder::new()
+            .period(Duration::from_millis(10))
+            .wake_strategy(WakeStrategy::Deferred)
+            .delay();
+        assert!(Pin::new(&mut delay).poll(&waker).is_pending());
+
+        let name = rx.recv().unwrap();
+        assert_eq!(
+            name.as_ref().map(|s| &s[..]),
+            Some("futures-native-timers-waker")
+        );
+        assert!(Pin::new(&mut delay).poll(&waker).is_ready());
+
+        let stream = TimerBuilder::new()
+            .period(Duration::from_millis(5))
+            .wake_strategy(WakeStrategy::Deferred)
+            .interval();
+        let ticks = block_on(stream.take(3).collect::<Vec<_>>());
+        assert_eq!(ticks.len(), 3);
+    }
+
+    #[test]
+    fn manual_wakes() {
+        use futures::task::ArcWake;
+        use std::pin::Pin;
+        use std::sync::atomic::AtomicBool;
+
+        struct Woken(AtomicBool);
+
+        impl ArcWake for Woken {
+            fn wake(arc_self: &Arc<Self>) {
+                arc_self.0.store(true, Release);
+            }
+        }
+
+        let woken = Arc::new(Woken(AtomicBool::new(false)));
+        let waker = ArcWake::into_waker(woken.clone());
+
+        let mut delay = TimerBuilder::new()
+            .period(Duration::from_millis(10))
+            .wake_strategy(WakeStrategy::Manual)
+            .delay();
+        assert!(Pin::new(&mut delay).poll(&waker).is_pending());
+
+        // the timer fires, but the task isn't woken until it's driven.
+        thread::sleep(Duration::from_millis(50));
+        assert!(delay.is_elapsed());
+        assert!(!woken.0.load(Acquire));
+
+        // other tests may have manual timers of their own, so only check for this one.
+        assert!(drive_ready_timers() >= 1);
+        assert!(woken.0.load(Acquire));
+        assert!(Pin::new(&mut delay).poll(&waker).is_ready());
+    }
+}
@@ -0,0 +1,383 @@
+use std::collections::HashMap;
+use std::io;
+use std::ptr;
+use std::sync::{mpsc, Arc, Mutex, Once, Weak};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use futures::executor::block_on;
+use futures::stream::StreamExt;
+
+use super::{saturating_add, Interval, TimerState};
+
+/// How many slots the wheel has. Deadlines more than one revolution out share a slot
+/// with nearer ones, and are skipped until their round comes up.
+const SLOTS: u64 = 512;
+
+/// A coarse-grained timer that drives any number of delays from a single os timer.
+///
+/// The wheel ticks at a fixed rate, and a delay created with [`Delay::new_on`] fires
+/// on the first tick at or after its deadline - so it may be up to one tick late, but
+/// never early. In exchange, tens of thousands of pending delays cost one os timer
+/// and a hash map entry each - on macOS that means a single dispatch source, instead
+/// of creating and tearing down a source for every short timeout.
+///
+/// The wheel is driven by a background thread, which exits once every handle to the
+/// wheel and every delay on it has been dropped.
+#[derive(Clone, Debug)]
+pub struct TimerWheel {
+    inner: Arc<Wheel>,
+}
+
+#[derive(Debug)]
+struct Wheel {
+    tick: Duration,
+    start: Instant,
+    slots: Mutex<Slots>,
+}
+
+#[derive(Debug)]
+struct Slots {
+    slots: Vec<HashMap<u64, Entry>>,
+    // which slot each registered entry is in, so it can be removed by id.
+    index: HashMap<u64, u64>,
+    next_tick: u64,
+    next_id: u64,
+}
+
+#[derive(Debug)]
+struct Entry {
+    deadline: u64,
+    period: Option<u64>,
+    state: Arc<TimerState>,
+    // the arming of the state the entry was inserted for.
+    generation: usize,
+}
+
+impl TimerWheel {
+    /// Creates a wheel that ticks every `tick`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tick` is zero, or if the os timer can't be created - see
+    /// [`TimerWheel::try_new`] for a version that returns the error instead.
+    pub fn new(tick: Duration) -> Self {
+        TimerWheel::try_new(tick).expect("error creating native timer!")
+    }
+
+    /// Like [`TimerWheel::new`], but returns the os error if the os timer can't be
+    /// created.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `tick` is zero.
+    pub fn try_new(tick: Duration) -> io::Result<Self> {
+        assert!(
+            tick > Duration::from_secs(0),
+            "a timer wheel needs a non-zero tick!"
+        );
+
+        let inner = Arc::new(Wheel {
+            tick,
+            start: Instant::now(),
+            slots: Mutex::new(Slots {
+                slots: (0..SLOTS).map(|_| HashMap::new()).collect(),
+                index: HashMap::new(),
+                next_tick: 1,
+                next_id: 0,
+            }),
+        });
+
+        // the os timer has to be created on the thread that drives it, so that its
+        // signals keep arriving for as long as the wheel is alive.
+        let weak = Arc::downgrade(&inner);
+        let (tx, rx) = mpsc::channel();
+
+        thread::Builder::new()
+            .name("futures-native-timers-wheel".into())
+            .spawn(move || match Interval::try_new(tick) {
+                Ok(ticks) => {
+                    let _ = tx.send(Ok(()));
+                    drive(ticks, weak);
+                }
+                Err(err) => {
+                    let _ = tx.send(Err(err));
+                }
+            })?;
+
+        rx.recv()
+            .expect("timer wheel thread died during startup!")?;
+
+        Ok(TimerWheel { inner })
+    }
+
+    /// Returns how often the wheel ticks.
+    pub fn tick(&self) -> Duration {
+        self.inner.tick
+    }
+
+    /// Makes this wheel take over once the process runs out of os timers - a delay or
+    /// interval that would fail to be created with [`TimerError::LimitReached`] is put
+    /// on the wheel instead, and keeps working at the wheel's coarser resolution.
+    ///
+    /// The wheel already has its own os timer, so it keeps taking timers however many
+    /// the process has. Only timers on the monotonic clock move over, and only one
+    /// wheel takes them - calling this on another one replaces this one.
+    pub fn use_for_overflow(&self) {
+        *overflow_wheel().lock().unwrap() = Some(self.clone());
+    }
+}
+
+static OVERFLOW_INIT: Once = Once::new();
+static mut OVERFLOW: *const Mutex<Option<TimerWheel>> = ptr::null();
+
+fn overflow_wheel() -> &'static Mutex<Option<TimerWheel>> {
+    OVERFLOW_INIT.call_once(|| unsafe {
+        OVERFLOW = Box::into_raw(Box::new(Mutex::new(None)));
+    });
+
+    unsafe { &*OVERFLOW }
+}
+
+/// Returns the wheel set with `TimerWheel::use_for_overflow`, if there is one.
+pub(crate) fn overflow() -> Option<TimerWheel> {
+    overflow_wheel().lock().unwrap().clone()
+}
+
+fn drive(mut ticks: Interval, wheel: Weak<Wheel>) {
+    loop {
+        block_on(ticks.next());
+
+        match wheel.upgrade() {
+            Some(wheel) => wheel.advance(),
+            None => break,
+        }
+    }
+}
+
+impl Wheel {
+    /// The first tick at or after `at` - or the last tick there is, for one too far out
+    /// to count.
+    fn tick_at(&self, at: Instant) -> u64 {
+        let nanos = (at - self.start).as_nanos();
+        let tick = self.tick.as_nanos();
+
+        ((nanos + tick - 1) / tick).min(u128::from(u64::max_value())) as u64
+    }
+
+    /// How many ticks `duration` covers, rounded up.
+    fn ticks(&self, duration: Duration) -> u64 {
+        let tick = self.tick.as_nanos();
+
+        let ticks = (duration.as_nanos() + tick - 1) / tick;
+        ticks.max(1).min(u128::from(u64::max_value())) as u64
+    }
+
+    fn insert(&self, state: Arc<TimerState>, start: Duration, period: Option<Duration>) -> u64 {
+        // a deadline too far out to be an `Instant` never comes round.
+        let deadline = self.tick_at(saturating_add(Instant::now(), start));
+        let period = period.map(|period| self.ticks(period));
+
+        let mut slots = self.slots.lock().unwrap();
+        let deadline = deadline.max(slots.next_tick);
+        let id = slots.next_id;
+        slots.next_id += 1;
+
+        let entry = Entry {
+            deadline,
+            period,
+            generation: state.generation(),
+            state,
+        };
+        slots.insert(id, entry);
+
+        id
+    }
+
+    fn remove(&self, id: u64) {
+        let mut slots = self.slots.lock().unwrap();
+
+        if let Some(slot) = slots.index.remove(&id) {
+            slots.slots[slot as usize].remove(&id);
+        }
+    }
+
+    /// Fires every entry whose deadline has passed.
+    fn advance(&self) {
+        let now = (Instant::now() - self.start).as_nanos() / self.tick.as_nanos();
+        let now = now as u64;
+        let mut fired = Vec::new();
+
+        {
+            let mut slots = self.slots.lock().unwrap();
+            if now < slots.next_tick {
+                return;
+            }
+
+            // after a long stall every slot only needs to be looked at once.
+            let last = now.min(slots.next_tick + SLOTS - 1);
+
+            for tick in slots.next_tick..=last {
+                let slot = (tick % SLOTS) as usize;
+
+                let due: Vec<u64> = slots.slots[slot]
+                    .iter()
+                    .filter(|(_, entry)| entry.deadline <= now)
+                    .map(|(&id, _)| id)
+                    .collect();
+
+                for id in due {
+                    let mut entry = slots.slots[slot].remove(&id).unwrap();
+                    slots.index.remove(&id);
+
+                    let mut count = 1;
+                    if let Some(period) = entry.period {
+                        entry.deadline = entry.deadline.saturating_add(period);
+                        while entry.deadline <= now {
+                            entry.deadline = entry.deadline.saturating_add(period);
+                            count += 1;
+                        }
+
+                        fired.push((entry.state.clone(), entry.generation, count));
+                        slots.insert(id, entry);
+                    } else {
+                        fired.push((entry.state, entry.generation, count));
+                    }
+                }
+            }
+
+            slots.next_tick = now + 1;
+        }
+
+        // wake everything outside the lock, so woken tasks can re-arm straight away -
+        // the states drop the ticks of anything re-armed or disarmed in the meantime.
+        for (state, generation, count) in fired {
+            state.fire_for(generation, count);
+        }
+    }
+}
+
+impl Slots {
+    fn insert(&mut self, id: u64, entry: Entry) {
+        let slot = entry.deadline % SLOTS;
+
+        self.index.insert(id, slot);
+        self.slots[slot as usize].insert(id, entry);
+    }
+}
+
+/// A timer registered with a [`TimerWheel`], with the same interface as the
+/// `NativeTimer`s so it can stand in for one.
+#[derive(Debug)]
+pub(crate) struct WheelTimer {
+    wheel: Arc<Wheel>,
+    state: Arc<TimerState>,
+    id: Option<u64>,
+}
+
+impl WheelTimer {
+    pub(crate) fn new(wheel: &TimerWheel, state: Arc<TimerState>) -> Self {
+        WheelTimer {
+            wheel: wheel.inner.clone(),
+            state,
+            id: None,
+        }
+    }
+
+    /// Creates another, unscheduled timer on the same wheel.
+    pub(crate) fn sibling(&self, state: Arc<TimerState>) -> Self {
+        WheelTimer {
+            wheel: self.wheel.clone(),
+            state,
+            id: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.id.is_some()
+    }
+
+    /// The wheel's tick already acts as the leeway, so this does nothing.
+    pub fn set_leeway(&mut self, _leeway: Duration) {}
+
+    pub fn init_delay(&mut self, delay: Duration) {
+        self.init(delay, None);
+    }
+
+    pub fn init_interval(&mut self, interval: Duration) {
+        self.init_interval_at(interval, interval);
+    }
+
+    pub fn init_interval_at(&mut self, start: Duration, interval: Duration) {
+        self.init(start, Some(interval));
+    }
+
+    fn init(&mut self, start: Duration, period: Option<Duration>) {
+        self.disarm();
+        self.id = Some(self.wheel.insert(self.state.clone(), start, period));
+    }
+
+    pub fn disarm(&mut self) {
+        if let Some(id) = self.id.take() {
+            self.wheel.remove(id);
+        }
+    }
+}
+
+impl Drop for WheelTimer {
+    fn drop(&mut self) {
+        self.disarm();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Delay;
+    use futures::prelude::*;
+    use futures::task::noop_waker_ref;
+    use std::pin::Pin;
+
+    #[test]
+    fn huge_delay_on_wheel() {
+        let wheel = TimerWheel::new(Duration::from_millis(5));
+        let mut delay = Delay::new_on(&wheel, Duration::from_secs(u64::max_value()));
+
+        // its deadline is past anything an `Instant` can hold, so it never comes round.
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+        thread::sleep(Duration::from_millis(30));
+        assert!(Pin::new(&mut delay).poll(noop_waker_ref()).is_pending());
+    }
+
+    #[test]
+    fn timer_wheel() {
+        let wheel = TimerWheel::new(Duration::from_millis(10));
+        assert_eq!(wheel.tick(), Duration::from_millis(10));
+
+        let start = Instant::now();
+        let mut delays: Vec<_> = (1..=100)
+            .map(|i| (i * 3, Delay::new_on(&wheel, Duration::from_millis(i * 3))))
+            .collect();
+
+        // arm them all at once.
+        for (_, delay) in &mut delays {
+            let _ = Pin::new(delay).poll(noop_waker_ref());
+        }
+
+        for (millis, delay) in delays {
+            block_on(delay);
+            assert!(start.elapsed() >= Duration::from_millis(millis));
+        }
+
+        // a delay can be reset while it's on the wheel, too.
+        let mut delay = Delay::new_on(&wheel, Duration::from_secs(60));
+        delay.reset(Duration::from_millis(20));
+
+        let start = Instant::now();
+        block_on(delay);
+
+        let elapsed = start.elapsed();
+        assert!(elapsed >= Duration::from_millis(20));
+        assert!(elapsed < Duration::from_secs(1));
+    }
+}